@@ -2,14 +2,22 @@
 
 root="$(picoref root)"
 cd "$root" || exit
+# One call gets the rows for every entry (fields are tab separated, starting with the key)
+list="$(mktemp)"
+trap 'rm -f "$list"' EXIT
+picoref list --sort added --reverse \
+  --template '{key}\t{year}\t{author}\t{title}\t{tags}' >"$list"
 RG_DEFAULT_COMMAND="rg -t toml -i -l"
-FZF_DEFAULT_COMMAND="rg -t toml --files | xargs -I% basename % .toml" fzf \
+# Searches the whole of each entry (abstract, notes and every other field), keeping the rows in order
+SEARCH="$RG_DEFAULT_COMMAND {q} | xargs -I% basename % .toml | awk -F'\t' 'NR == FNR { keys[\$1]; next } \$1 in keys' - '$list'"
+fzf <"$list" \
   -m \
   -e \
-  --ansi \
   --disabled \
   --reverse \
+  --delimiter '\t' \
   --bind "ctrl-a:select-all" \
-  --bind "f12:execute-silent:(subl -b {})" \
-  --bind "change:reload:$RG_DEFAULT_COMMAND {q} | xargs -I% basename % .toml" \
-  --preview 'picoref markdown {} | glow --style auto'
+  --bind "f12:execute-silent:(subl -b {1}/{1}.toml)" \
+  --bind "change:reload:$SEARCH" \
+  --preview 'picoref markdown {1} | glow --style auto' |
+  cut -f1
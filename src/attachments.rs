@@ -0,0 +1,119 @@
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::ops;
+
+/// What an attachment is for
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// Supplementary material
+    Supplement,
+    /// The accepted (author) manuscript
+    Manuscript,
+    /// Presentation slides
+    Slides,
+    /// Data associated with the reference
+    Data,
+    /// Anything else
+    Other,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self
+            .to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .to_string();
+        f.write_str(&s)
+    }
+}
+
+/// A file stored in an entry's directory (other than its main PDF)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attachment {
+    pub role: Role,
+    /// Name of the file, relative to the entry's directory
+    pub filename: String,
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+}
+
+/// Guesses the mime type of a file from its extension
+pub fn mime_type(filename: &str) -> &'static str {
+    let ext = Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match ext.as_str() {
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "csv" => "text/csv",
+        "tsv" => "text/tab-separated-values",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "mp4" => "video/mp4",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Copies `file` into the entry's directory and returns the attachment describing it
+pub fn attach(
+    root: &Path,
+    key: &str,
+    file: &Path,
+    role: Role,
+    source_url: Option<String>,
+) -> Result<Attachment> {
+    let filename = file
+        .file_name()
+        .context("Not a valid file name")?
+        .to_str()
+        .context("Path contains unicode")?
+        .to_string();
+    let dest = ops::attachment_path(root, key, &filename);
+    let reserved = [
+        ops::data_path(root, key),
+        ops::pdf_path(root, key),
+        ops::notes_path(root, key),
+    ];
+    if reserved.contains(&dest) {
+        anyhow::bail!(
+            "{filename} is used by picoref itself, so rename the file before attaching it"
+        );
+    }
+    if dest.exists() {
+        anyhow::bail!("A file already exists at: {}", dest.to_string_lossy());
+    }
+    std::fs::copy(file, dest)?;
+    Ok(Attachment {
+        role,
+        mime_type: mime_type(&filename).to_string(),
+        filename,
+        source_url,
+    })
+}
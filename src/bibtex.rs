@@ -1,83 +1,173 @@
-use biblatex::{
-    Chunk, Date, DateValue, Datetime, Entry, EntryType, PermissiveType, Person, Spanned,
-};
+use std::path::Path;
+
+use biblatex::{Chunk, Date, DateValue, Datetime, Entry, EntryType, PermissiveType, Spanned};
 use serde_json::Value;
 
 use crate::entry::EntryData;
+use crate::{names, ops};
 
 fn chunks(s: &str) -> Vec<Spanned<Chunk>> {
     vec![Spanned::detached(Chunk::Normal(s.to_string()))]
 }
 
+/// Maps a CSL 1.0.2 item type onto the closest BibLaTeX entry type.
+///
+/// Types without a BibLaTeX equivalent fall back to `@misc`, with the original CSL type
+/// preserved in the `type` field (see `fallback_subtype`) so that no information is lost.
 fn map_entry_type(csl_type: &str) -> EntryType {
     match csl_type {
-        "article-journal" | "article" | "article-magazine" | "article-newspaper" => {
-            EntryType::Article
-        }
-        "book" => EntryType::Book,
+        "article-journal" | "article" | "article-magazine" | "article-newspaper" | "review"
+        | "review-book" => EntryType::Article,
+        "book" | "classic" => EntryType::Book,
         "chapter" => EntryType::InCollection,
+        "collection" => EntryType::Collection,
+        "entry" | "entry-dictionary" | "entry-encyclopedia" => EntryType::InReference,
         "paper-conference" => EntryType::InProceedings,
+        "periodical" => EntryType::Periodical,
+        "pamphlet" => EntryType::Booklet,
         "report" => EntryType::Report,
         "thesis" => EntryType::Thesis,
-        "webpage" | "post-weblog" => EntryType::Online,
+        "patent" => EntryType::Patent,
+        "manuscript" | "speech" => EntryType::Unpublished,
+        "webpage" | "post" | "post-weblog" => EntryType::Online,
         "dataset" => EntryType::Dataset,
+        "software" => EntryType::Software,
         _ => EntryType::Misc,
     }
 }
 
-fn parse_persons(value: &Value) -> Option<Vec<Person>> {
-    let arr = value.as_array()?;
-    let persons: Vec<_> = arr
+/// A human readable `type` for CSL types that BibLaTeX has no entry type for
+/// (e.g. `motion_picture` becomes "Motion picture").
+fn fallback_subtype(csl_type: &str) -> Option<String> {
+    match csl_type {
+        "motion_picture"
+        | "broadcast"
+        | "song"
+        | "musical_score"
+        | "graphic"
+        | "map"
+        | "figure"
+        | "interview"
+        | "performance"
+        | "personal_communication"
+        | "event"
+        | "bill"
+        | "legislation"
+        | "legal_case"
+        | "regulation"
+        | "treaty"
+        | "hearing"
+        | "standard"
+        | "document" => {
+            let mut s = csl_type.replace(['_', '-'], " ");
+            s[..1].make_ascii_uppercase();
+            Some(s)
+        }
+        _ => None,
+    }
+}
+
+/// Maps a CSL `genre` onto the localization keys BibLaTeX understands for theses
+/// (`phdthesis`, `mathesis`), leaving any other genre untouched.
+fn thesis_type(genre: &str) -> String {
+    let lower = genre.to_lowercase();
+    if ["phd", "ph.d", "doctor", "dissertation"]
         .iter()
-        .filter_map(|p| {
-            let family = p.get("family")?.as_str()?;
-            let given = p.get("given").and_then(|g| g.as_str()).unwrap_or("");
-            Some(Person {
-                name: family.to_string(),
-                given_name: given.to_string(),
-                prefix: String::new(),
-                suffix: String::new(),
-            })
-        })
-        .collect();
-    if persons.is_empty() {
-        None
+        .any(|k| lower.contains(k))
+    {
+        "phdthesis".to_string()
+    } else if lower.contains("master") {
+        "mathesis".to_string()
     } else {
-        Some(persons)
+        genre.to_string()
     }
 }
 
-fn persons_to_bib_string(persons: &[Person]) -> String {
-    persons
-        .iter()
-        .map(|p| {
-            if p.given_name.is_empty() {
-                p.name.clone()
-            } else {
-                format!("{}, {}", p.name, p.given_name)
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" and ")
+/// Reads a CSL value that may be either a string or a number as a string.
+fn as_string(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .map(|s| s.to_string())
+        .or_else(|| value.as_i64().map(|n| n.to_string()))
+        .filter(|s| !s.is_empty())
 }
 
-fn parse_date(value: &Value) -> Option<PermissiveType<Date>> {
-    let parts = value.get("date-parts")?.as_array()?.first()?.as_array()?;
-    let year = parts.first()?.as_i64()? as i32;
+/// Reads a CSL value that may be a string or an array of strings (e.g. `ISSN`), taking the first.
+fn first_string(value: &Value) -> Option<String> {
+    as_string(value).or_else(|| as_string(value.as_array()?.first()?))
+}
+
+fn permissive_int(value: &Value) -> Option<PermissiveType<i64>> {
+    if let Some(n) = value.as_i64() {
+        return Some(PermissiveType::Typed(n));
+    }
+    let s = value.as_str()?;
+    Some(match s.parse::<i64>() {
+        Ok(n) => PermissiveType::Typed(n),
+        Err(_) => PermissiveType::Chunks(chunks(s)),
+    })
+}
+
+fn date_part(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+fn parse_datetime(parts: &Value) -> Option<Datetime> {
+    let parts = parts.as_array()?;
+    let year = date_part(parts.first()?)? as i32;
+    // BibLaTeX months and days start at zero. Seasons (which CSL writes as months 13 to 16, or 21
+    // to 24 in EDTF) have no equivalent, so only the year is kept for them.
     let month = parts
         .get(1)
-        .and_then(|m| m.as_u64())
-        .map(|m| m.saturating_sub(1) as u8);
-    let day = parts.get(2).and_then(|d| d.as_u64()).map(|d| d as u8);
+        .and_then(date_part)
+        .filter(|m| (1..=12).contains(m))
+        .map(|m| (m - 1) as u8);
+    let day = parts
+        .get(2)
+        .and_then(date_part)
+        .filter(|d| month.is_some() && (1..=31).contains(d))
+        .map(|d| (d - 1) as u8);
+    Some(Datetime {
+        year,
+        month,
+        day,
+        time: None,
+    })
+}
+
+/// Converts a CSL date variable, including ranges (two `date-parts`) and `circa` dates.
+/// Dates that only have a `literal` or `raw` form are passed through verbatim.
+fn parse_date(value: &Value) -> Option<PermissiveType<Date>> {
+    let parts = value
+        .get("date-parts")
+        .and_then(|p| p.as_array())
+        .map(|p| p.iter().filter_map(parse_datetime).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let date_value = match parts[..] {
+        [start] => DateValue::At(start),
+        [start, end, ..] if start == end => DateValue::At(start),
+        [start, end, ..] => DateValue::Between(start, end),
+        [] => {
+            return value
+                .get("literal")
+                .or_else(|| value.get("raw"))
+                .and_then(as_string)
+                .map(|s| PermissiveType::Chunks(chunks(&s)))
+        }
+    };
+    let approximate = value.get("circa").is_some_and(|c| match c {
+        Value::Bool(b) => *b,
+        Value::Null => false,
+        Value::Number(n) => n.as_i64() != Some(0),
+        Value::String(s) => !s.is_empty() && s != "0" && s != "false",
+        _ => true,
+    });
     Some(PermissiveType::Typed(Date {
-        value: DateValue::At(Datetime {
-            year,
-            month,
-            day,
-            time: None,
-        }),
+        value: date_value,
         uncertain: false,
-        approximate: false,
+        approximate,
     }))
 }
 
@@ -90,11 +180,45 @@ fn normalize_pages(pages: &str) -> String {
         .join("--")
 }
 
-pub fn to_bibtex(entries: impl IntoIterator<Item = EntryData>) -> anyhow::Result<String> {
+/// Escapes the characters that separate the parts of a JabRef `file` field
+fn escape_file_part(part: &str) -> String {
+    let mut escaped = String::with_capacity(part.len());
+    for c in part.chars() {
+        if matches!(c, '\\' | ':' | ';') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The PDF and attachments of an entry, in the JabRef `file` format
+/// ("description:path:mime type", separated by semicolons)
+fn file_field(root: &Path, key: &str, data: &EntryData) -> Option<String> {
+    let file = |description: &str, path: &Path, mime_type: &str| {
+        [description, &path.to_string_lossy(), mime_type]
+            .map(escape_file_part)
+            .join(":")
+    };
+    let pdf = ops::pdf_path(root, key);
+    let pdf = pdf.exists().then(|| file("", &pdf, "application/pdf"));
+    let attachments = data.attachments.iter().map(|a| {
+        let path = ops::attachment_path(root, key, &a.filename);
+        file(&a.role.to_string(), &path, &a.mime_type)
+    });
+    let files: Vec<String> = pdf.into_iter().chain(attachments).collect();
+    (!files.is_empty()).then(|| files.join(";"))
+}
+
+pub fn to_bibtex(
+    root: &Path,
+    entries: impl IntoIterator<Item = (String, EntryData)>,
+) -> anyhow::Result<String> {
     let mut output = Vec::new();
 
-    for entry_data in entries {
+    for (picoref_key, entry_data) in entries {
         let fields = &entry_data.fields;
+        let get_str = |name: &str| fields.get(name).and_then(as_string);
 
         let csl_type = fields
             .get("type")
@@ -102,6 +226,9 @@ pub fn to_bibtex(entries: impl IntoIterator<Item = EntryData>) -> anyhow::Result
             .unwrap_or("misc");
         let entry_type = map_entry_type(csl_type);
         let is_article = matches!(entry_type, EntryType::Article);
+        // BibLaTeX stores the issuing body of reports and theses in `institution`, whereas CSL
+        // uses `publisher` for both.
+        let has_institution = matches!(entry_type, EntryType::Report | EntryType::Thesis);
 
         let key = fields
             .get("citation-key")
@@ -110,106 +237,346 @@ pub fn to_bibtex(entries: impl IntoIterator<Item = EntryData>) -> anyhow::Result
             .unwrap_or("unknown")
             .to_string();
 
-        let mut entry = Entry::new(key, entry_type);
+        let mut entry = Entry::new(key, entry_type.clone());
+
+        if let Some(title) = get_str("title") {
+            entry.set_title(chunks(&title));
+        }
+
+        if let Some(short_title) = get_str("title-short") {
+            entry.set_short_title(chunks(&short_title));
+        }
 
-        if let Some(title) = fields.get("title").and_then(|t| t.as_str()) {
-            entry.set_title(chunks(title));
+        if let Some(original_title) = get_str("original-title") {
+            entry.set("origtitle", chunks(&original_title));
         }
 
-        if let Some(persons) = fields.get("author").and_then(parse_persons) {
-            entry.set_author(persons);
+        let authors = fields
+            .get("author")
+            .map(names::parse_names)
+            .unwrap_or_default();
+        if !authors.is_empty() {
+            entry.set("author", names::to_bib_chunks(&authors));
         }
 
-        if let Some(persons) = fields.get("editor").and_then(parse_persons) {
-            entry.set("editor", chunks(&persons_to_bib_string(&persons)));
+        let editors = fields
+            .get("editor")
+            .map(names::parse_names)
+            .unwrap_or_default();
+        if !editors.is_empty() {
+            entry.set("editor", names::to_bib_chunks(&editors));
         }
 
-        if let Some(container) = fields.get("container-title").and_then(|t| t.as_str()) {
+        let translators = fields
+            .get("translator")
+            .map(names::parse_names)
+            .unwrap_or_default();
+        if !translators.is_empty() {
+            entry.set("translator", names::to_bib_chunks(&translators));
+        }
+
+        if let Some(container) = get_str("container-title") {
             if is_article {
-                entry.set_journal_title(chunks(container));
+                entry.set_journal_title(chunks(&container));
             } else {
-                entry.set_book_title(chunks(container));
+                entry.set_book_title(chunks(&container));
             }
         }
 
-        if let Some(doi) = fields.get("DOI").and_then(|d| d.as_str()) {
-            entry.set_doi(doi.to_string());
+        if let Some(short_container) = get_str("container-title-short") {
+            if is_article {
+                entry.set_short_journal(chunks(&short_container));
+            }
         }
 
-        if let Some(url) = fields.get("URL").and_then(|u| u.as_str()) {
-            entry.set_url(url.to_string());
+        if let Some(series) = get_str("collection-title") {
+            entry.set_series(chunks(&series));
+        }
+
+        if let Some(doi) = get_str("DOI") {
+            entry.set_doi(doi);
+        }
+
+        if let Some(url) = get_str("URL") {
+            entry.set_url(url);
+        }
+
+        if get_str("archive").as_deref() == Some("arXiv") {
+            if let Some(eprint) = get_str("archive_location") {
+                entry.set_eprint(eprint);
+                entry.set_eprint_type(chunks("arxiv"));
+            }
+            if let Some(class) = get_str("archive_collection") {
+                entry.set_eprint_class(chunks(&class));
+            }
         }
 
         if let Some(date) = fields.get("issued").and_then(parse_date) {
             entry.set_date(date);
         }
 
-        if let Some(vol) = fields.get("volume") {
-            if let Some(n) = vol.as_i64() {
-                entry.set_volume(PermissiveType::Typed(n));
-            } else if let Some(s) = vol.as_str() {
-                match s.parse::<i64>() {
-                    Ok(n) => entry.set_volume(PermissiveType::Typed(n)),
-                    Err(_) => entry.set_volume(PermissiveType::Chunks(chunks(s))),
-                }
-            }
+        if let Some(date) = fields.get("original-date").and_then(parse_date) {
+            entry.set_orig_date(date);
         }
 
-        if let Some(num) = fields.get("issue").or_else(|| fields.get("number")) {
-            let s = num
-                .as_str()
-                .map(|s| s.to_string())
-                .or_else(|| num.as_i64().map(|n| n.to_string()));
-            if let Some(s) = s {
-                entry.set_number(chunks(&s));
-            }
+        if let Some(date) = fields.get("accessed").and_then(parse_date) {
+            entry.set_url_date(date);
         }
 
-        if let Some(pages) = fields.get("page").and_then(|p| p.as_str()) {
-            entry.set_pages(PermissiveType::Chunks(chunks(&normalize_pages(pages))));
+        if let Some(date) = fields.get("event-date").and_then(parse_date) {
+            entry.set_event_date(date);
         }
 
-        if let Some(publisher) = fields.get("publisher").and_then(|p| p.as_str()) {
-            entry.set_publisher(vec![chunks(publisher)]);
+        // `event` is the CSL 1.0.1 name of `event-title`
+        if let Some(event) = get_str("event-title").or_else(|| get_str("event")) {
+            entry.set_eventtitle(chunks(&event));
         }
 
-        if let Some(issn) = fields.get("ISSN") {
-            let s = issn
-                .as_str()
-                .map(|s| s.to_string())
-                .or_else(|| issn.as_array()?.first()?.as_str().map(|s| s.to_string()));
-            if let Some(s) = s {
-                entry.set_issn(chunks(&s));
-            }
+        if let Some(venue) = get_str("event-place") {
+            entry.set_venue(chunks(&venue));
+        }
+
+        if let Some(vol) = fields.get("volume").and_then(permissive_int) {
+            entry.set_volume(vol);
         }
 
-        if let Some(isbn) = fields.get("ISBN") {
-            let s = isbn
-                .as_str()
-                .map(|s| s.to_string())
-                .or_else(|| isbn.as_array()?.first()?.as_str().map(|s| s.to_string()));
-            if let Some(s) = s {
-                entry.set_isbn(chunks(&s));
+        if let Some(volumes) = fields.get("number-of-volumes").and_then(|v| {
+            v.as_i64()
+                .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+        }) {
+            entry.set_volumes(volumes);
+        }
+
+        // BibLaTeX uses `number` for the issue of a journal, the number of a report or patent,
+        // and the number of a book within its series.
+        let number = if is_article {
+            get_str("issue").or_else(|| get_str("number"))
+        } else {
+            get_str("number").or_else(|| get_str("collection-number"))
+        };
+        if let Some(number) = number {
+            entry.set_number(chunks(&number));
+        }
+        if !is_article {
+            if let Some(issue) = get_str("issue") {
+                entry.set_issue(chunks(&issue));
             }
         }
 
-        if let Some(abs) = fields.get("abstract").and_then(|a| a.as_str()) {
-            entry.set_abstract_(chunks(abs));
+        if let Some(chapter) = get_str("chapter-number") {
+            entry.set_chapter(chunks(&chapter));
         }
 
-        if let Some(ed) = fields.get("edition") {
-            if let Some(n) = ed.as_i64() {
-                entry.set_edition(PermissiveType::Typed(n));
-            } else if let Some(s) = ed.as_str() {
-                match s.parse::<i64>() {
-                    Ok(n) => entry.set_edition(PermissiveType::Typed(n)),
-                    Err(_) => entry.set_edition(PermissiveType::Chunks(chunks(s))),
-                }
+        if let Some(pages) = get_str("page") {
+            entry.set_pages(PermissiveType::Chunks(chunks(&normalize_pages(&pages))));
+        }
+
+        if let Some(page_total) = get_str("number-of-pages") {
+            entry.set_page_total(chunks(&page_total));
+        }
+
+        if let Some(publisher) = get_str("publisher") {
+            if has_institution {
+                entry.set_institution(chunks(&publisher));
+            } else {
+                entry.set_publisher(vec![chunks(&publisher)]);
             }
         }
 
-        output.push(entry.to_biblatex_string());
+        if let Some(place) = get_str("publisher-place") {
+            entry.set_location(chunks(&place));
+        }
+
+        let subtype = match entry_type {
+            EntryType::Thesis => get_str("genre").map(|g| thesis_type(&g)),
+            EntryType::Report => get_str("genre").or_else(|| Some("techreport".to_string())),
+            EntryType::Misc => get_str("genre").or_else(|| fallback_subtype(csl_type)),
+            _ => get_str("genre"),
+        };
+        if let Some(subtype) = subtype {
+            // Not `set_type_`, which adds braces that stop BibLaTeX localizing e.g. `phdthesis`
+            entry.set("type", chunks(&subtype));
+        }
+
+        if let Some(medium) = get_str("medium") {
+            entry.set_how_published(chunks(&medium));
+        }
+
+        if let Some(version) = get_str("version") {
+            entry.set_version(chunks(&version));
+        }
+
+        if let Some(status) = get_str("status") {
+            entry.set_pubstate(chunks(&status));
+        }
+
+        if let Some(issn) = fields.get("ISSN").and_then(first_string) {
+            entry.set_issn(chunks(&issn));
+        }
+
+        if let Some(isbn) = fields.get("ISBN").and_then(first_string) {
+            entry.set_isbn(chunks(&isbn));
+        }
+
+        if let Some(abs) = get_str("abstract") {
+            entry.set_abstract_(chunks(&abs));
+        }
+
+        if let Some(keywords) = get_str("keyword") {
+            entry.set_keywords(chunks(&keywords));
+        }
+
+        if let Some(note) = get_str("note") {
+            entry.set_note(chunks(&note));
+        }
+
+        if let Some(language) = get_str("language") {
+            entry.set("language", chunks(&language));
+        }
+
+        if let Some(ed) = fields.get("edition").and_then(permissive_int) {
+            entry.set_edition(ed);
+        }
+
+        let mut bib = entry.to_biblatex_string();
+        // Added to the output directly, since JabRef reads the field literally and the biblatex
+        // crate would escape the backslashes that `escape_file_part` adds
+        if let Some(files) = file_field(root, &picoref_key, &entry_data) {
+            let end = bib.rfind('}').expect("entries end with a brace");
+            bib.insert_str(end, &format!("file = {{{files}}},\n"));
+        }
+        output.push(bib);
     }
 
     Ok(output.join("\n"))
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn export(fields: Value) -> String {
+        let data = EntryData {
+            fields: serde_json::from_value(fields).unwrap(),
+            ..Default::default()
+        };
+        to_bibtex(Path::new("/nonexistent"), [("key".to_string(), data)]).unwrap()
+    }
+
+    #[test]
+    fn exports_attachments() {
+        let data = EntryData {
+            fields: serde_json::from_value(json!({"type": "article", "title": "T"})).unwrap(),
+            attachments: serde_json::from_value(json!([
+                {"role": "supplement", "filename": "Supplement: data.csv", "mime_type": "text/csv"},
+                {"role": "slides", "filename": "talk;v2\\final.pdf", "mime_type": "application/pdf"},
+            ]))
+            .unwrap(),
+            ..Default::default()
+        };
+        let bib = to_bibtex(Path::new("/lib"), [("key".to_string(), data)]).unwrap();
+        assert!(
+            bib.ends_with(concat!(
+                r"file = {supplement:/lib/key/Supplement\: data.csv:text/csv;",
+                r"slides:/lib/key/talk\;v2\\final.pdf:application/pdf},",
+                "\n}"
+            )),
+            "{bib}"
+        );
+        // Still valid BibTeX
+        let parsed = biblatex::Bibliography::parse(&bib).unwrap();
+        assert!(parsed.get("unknown").unwrap().get("file").is_some());
+    }
+
+    fn date(value: Value) -> Date {
+        match parse_date(&value) {
+            Some(PermissiveType::Typed(date)) => date,
+            other => panic!("expected a date, got {other:?}"),
+        }
+    }
+
+    fn datetime(year: i32, month: Option<u8>, day: Option<u8>) -> Datetime {
+        Datetime {
+            year,
+            month,
+            day,
+            time: None,
+        }
+    }
+
+    #[test]
+    fn maps_types() {
+        let thesis = export(json!({"id": "key", "type": "thesis", "genre": "PhD dissertation"}));
+        assert!(thesis.starts_with("@thesis{key,"), "{thesis}");
+        assert!(thesis.contains("type = {phdthesis}"), "{thesis}");
+        assert_eq!(thesis_type("Master's thesis"), "mathesis");
+        assert_eq!(thesis_type("Habilitation"), "Habilitation");
+
+        let report = export(json!({"id": "key", "type": "report", "publisher": "NASA"}));
+        assert!(report.starts_with("@report{key,"), "{report}");
+        assert!(report.contains("type = {techreport}"), "{report}");
+        assert!(report.contains("institution = {NASA}"), "{report}");
+
+        let paper = export(json!({
+            "id": "key",
+            "type": "paper-conference",
+            "container-title": "Proceedings of NeurIPS",
+        }));
+        assert!(paper.starts_with("@inproceedings{key,"), "{paper}");
+        assert!(
+            paper.contains("booktitle = {Proceedings of NeurIPS}"),
+            "{paper}"
+        );
+
+        let film = export(json!({"id": "key", "type": "motion_picture"}));
+        assert!(film.starts_with("@misc{key,"), "{film}");
+        assert!(film.contains("type = {Motion picture}"), "{film}");
+        assert_eq!(fallback_subtype("article-journal"), None);
+    }
+
+    #[test]
+    fn converts_dates() {
+        // Months and days are zero-indexed in BibLaTeX
+        let single = date(json!({"date-parts": [[2020, 3, 15]]}));
+        assert_eq!(
+            single.value,
+            DateValue::At(datetime(2020, Some(2), Some(14)))
+        );
+        assert!(!single.approximate);
+
+        let range = date(json!({"date-parts": [[2019, 12], [2020, 1]]}));
+        assert_eq!(
+            range.value,
+            DateValue::Between(
+                datetime(2019, Some(11), None),
+                datetime(2020, Some(0), None)
+            )
+        );
+        let same = date(json!({"date-parts": [[2020], [2020]]}));
+        assert_eq!(same.value, DateValue::At(datetime(2020, None, None)));
+
+        // Seasons only keep the year
+        for season in [13, 16, 21] {
+            let date = date(json!({"date-parts": [[2020, season]]}));
+            assert_eq!(date.value, DateValue::At(datetime(2020, None, None)));
+        }
+
+        let circa = date(json!({"date-parts": [["1850"]], "circa": true}));
+        assert_eq!(circa.value, DateValue::At(datetime(1850, None, None)));
+        assert!(circa.approximate);
+        assert!(!date(json!({"date-parts": [[1850]], "circa": 0})).approximate);
+
+        assert!(matches!(
+            parse_date(&json!({"literal": "Spring 2020"})),
+            Some(PermissiveType::Chunks(_))
+        ));
+        let exported = export(json!({
+            "id": "key",
+            "type": "book",
+            "issued": {"date-parts": [[2019, 12], [2020, 1]]},
+        }));
+        assert!(exported.contains("date = {2019-12/2020-01}"), "{exported}");
+    }
+}
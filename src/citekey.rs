@@ -4,6 +4,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 
 use crate::entry::EntryData;
+use crate::names::Name;
 use crate::regex::cap_as_str;
 
 const STOPWORDS: [&str; 22] = [
@@ -23,21 +24,8 @@ pub fn get_key(data: &EntryData) -> Result<String> {
         .get("author")
         .and_then(|authors| authors.get(0))
         .and_then(|author| {
-            author
-                .get("family")?
-                .as_str()
-                .map(|s| deunicode_with_tofu(s, "").to_lowercase())
-                .or_else(|| {
-                    Some(
-                        author
-                            .get("literal")?
-                            .as_str()
-                            .map(|s| deunicode_with_tofu(s, "").to_lowercase())?
-                            .split_whitespace()
-                            .last()?
-                            .to_string(),
-                    )
-                })
+            Name::from_value(author)
+                .map(|n| deunicode_with_tofu(&n.key_part(), "").to_lowercase())
                 .or_else(|| {
                     author
                         .get("source")
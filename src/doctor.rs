@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use crate::ops;
+use crate::tags::Vocabulary;
+
+/// Checks a single entry for problems, returning a description of each one found
+pub fn check_entry(root: &Path, key: &str, vocabulary: Option<&Vocabulary>) -> Vec<String> {
+    let mut problems = Vec::new();
+    let entry = match ops::read_entry(&ops::data_path(root, key)) {
+        Ok(entry) => entry,
+        Err(e) => return vec![format!("{e:#}")],
+    };
+    if let Err(e) = entry.data.validate() {
+        problems.push(format!("{e:#}"));
+    }
+    if let Some(vocabulary) = vocabulary {
+        problems.extend(entry.data.tags.iter().filter_map(|t| vocabulary.check(t)));
+    }
+
+    let mut known_files = vec![
+        format!("{key}.toml"),
+        format!("{key}.pdf"),
+        "notes.md".to_string(),
+    ];
+    for attachment in entry.data.attachments.iter() {
+        if !ops::attachment_path(root, key, &attachment.filename).exists() {
+            problems.push(format!(
+                "Missing {} attachment: {}",
+                attachment.role, attachment.filename
+            ));
+        }
+        known_files.push(attachment.filename.clone());
+    }
+
+    match std::fs::read_dir(ops::entry_root_path(root, key)) {
+        Ok(files) => {
+            for file in files.flatten() {
+                let name = file.file_name().to_string_lossy().to_string();
+                if !known_files.contains(&name) {
+                    problems.push(format!(
+                        "Untracked file (add it with `picoref attach`): {name}"
+                    ));
+                }
+            }
+        }
+        Err(e) => problems.push(e.to_string()),
+    }
+    problems
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static PREFIX_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(?:(?:https?://)?(?:dx\.|www\.)?doi\.org/|doi:?\s*)").unwrap());
+/// See https://www.crossref.org/blog/dois-and-matching-regular-expressions/
+static DOI_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^10\.\d{4,9}(?:\.\d+)*/\S+$").unwrap());
+
+/// A DOI, normalized from the forms that it is commonly written in
+/// (e.g. "doi:10.1000/xyz", "https://doi.org/10.1000/xyz", or with punctuation from a sentence).
+/// DOIs are case-insensitive, so comparisons ignore case, but the original case is kept for display.
+#[derive(Debug, Clone)]
+pub struct Doi(String);
+
+/// Decodes `%XX` escapes (e.g. from a DOI copied out of a URL), leaving invalid escapes as-is
+fn percent_decode(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).context("DOI is not valid UTF-8 after decoding")
+}
+
+/// Removes punctuation that was copied along with the DOI, keeping closing brackets that are
+/// part of the DOI itself (e.g. "10.1016/0092-8674(88)90498-8")
+fn trim_trailing_punctuation(mut s: &str) -> &str {
+    loop {
+        let Some(last) = s.chars().last() else {
+            return s;
+        };
+        let unbalanced = |open, close| s.matches(close).count() > s.matches(open).count();
+        let trim = match last {
+            '.' | ',' | ';' | ':' | '"' | '\'' => true,
+            ')' => unbalanced('(', ')'),
+            ']' => unbalanced('[', ']'),
+            '>' => unbalanced('<', '>'),
+            _ => false,
+        };
+        if !trim {
+            return s;
+        }
+        s = &s[..s.len() - last.len_utf8()];
+    }
+}
+
+impl Doi {
+    pub fn parse(input: &str) -> Result<Self> {
+        let trimmed = input.trim();
+        let stripped = PREFIX_RE.replace(trimmed, "");
+        let decoded = percent_decode(&stripped)?;
+        let doi = trim_trailing_punctuation(decoded.trim());
+        if !DOI_RE.is_match(doi) {
+            anyhow::bail!("Not a valid DOI: {input:?}");
+        }
+        Ok(Self(doi.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The form used to compare DOIs
+    pub fn key(&self) -> String {
+        self.0.to_lowercase()
+    }
+}
+
+impl PartialEq for Doi {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Doi {}
+
+impl Hash for Doi {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+impl fmt::Display for Doi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Doi {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_prefixes() {
+        for input in [
+            "10.1000/xyz123",
+            "doi:10.1000/xyz123",
+            "DOI: 10.1000/xyz123",
+            "https://doi.org/10.1000/xyz123",
+            "http://dx.doi.org/10.1000/xyz123",
+            " doi.org/10.1000/xyz123 ",
+        ] {
+            assert_eq!(Doi::parse(input).unwrap().as_str(), "10.1000/xyz123");
+        }
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(
+            Doi::parse("https://doi.org/10.1002%2F%28SICI%291097-4636")
+                .unwrap()
+                .as_str(),
+            "10.1002/(SICI)1097-4636"
+        );
+    }
+
+    #[test]
+    fn trims_trailing_punctuation() {
+        assert_eq!(
+            Doi::parse("10.1000/xyz123.").unwrap().as_str(),
+            "10.1000/xyz123"
+        );
+        assert_eq!(
+            Doi::parse("10.1016/0092-8674(88)90498-8").unwrap().as_str(),
+            "10.1016/0092-8674(88)90498-8"
+        );
+        assert_eq!(
+            Doi::parse("doi:10.1000/ABC(1)).").unwrap().as_str(),
+            "10.1000/ABC(1)"
+        );
+    }
+
+    #[test]
+    fn compares_case_insensitively() {
+        let a = Doi::parse("10.1000/ABC").unwrap();
+        let b = Doi::parse("https://doi.org/10.1000/abc").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), "10.1000/ABC");
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        for input in [
+            "",
+            "10.1000",
+            "11.1000/abc",
+            "10.12/abc",
+            "not a doi",
+            "see 10.1000/xyz123",
+        ] {
+            assert!(Doi::parse(input).is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+
+/// Opens `path` in the user's editor (`$VISUAL`, then `$EDITOR`, falling back to `vi`)
+/// and waits for it to exit.
+pub fn open(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // Editors are often configured with arguments (e.g. "code --wait")
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("No editor configured")?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Could not launch editor: {editor}"))?;
+    if !status.success() {
+        anyhow::bail!("Editor exited with {status}");
+    }
+    Ok(())
+}
+
+/// Asks the user a yes / no question on the terminal, returning `default` if they just press
+/// enter (destructive actions should default to no)
+pub fn confirm(question: &str, default: bool) -> Result<bool> {
+    eprint!("{question} {} ", if default { "[Y/n]" } else { "[y/N]" });
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => true,
+        "n" | "no" => false,
+        _ => default,
+    })
+}
+
+/// Opens `path` with the default application for its type, without waiting for it to exit
+pub fn open_with_system(path: &Path) -> Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Could not launch {opener}"))?;
+    Ok(())
+}
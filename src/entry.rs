@@ -1,24 +1,49 @@
+use std::fmt;
+
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use serde_json::Map;
+use serde_json::{Map, Value};
 
-use crate::fetch::{arxiv, biorxiv, osf};
+use crate::attachments::Attachment;
+use crate::doi::Doi;
+use crate::fetch::{
+    arxiv, biorxiv, chemrxiv, eartharxiv, osf, preprints, research_square, ssrn, zenodo,
+};
 use crate::regex::cap_as_str;
 
 #[derive(Debug)]
 pub enum Source {
     Arxiv(String),
-    Biorxiv(String),
+    Biorxiv(biorxiv::PreprintId),
     Osf(String),
+    Ssrn(String),
+    ResearchSquare(String),
+    Chemrxiv(String),
+    Preprints(String),
+    Zenodo(String),
+    Eartharxiv(String),
     Other(String),
 }
 
 impl From<String> for Source {
     fn from(value: String) -> Self {
+        if let Some(id) = biorxiv::PreprintId::parse(&value) {
+            return Self::Biorxiv(id);
+        }
         for (t, re) in [
             (Self::Arxiv as fn(String) -> Self, &arxiv::RE),
-            (Self::Biorxiv as fn(String) -> Self, &biorxiv::RE),
             (Self::Osf as fn(String) -> Self, &osf::RE),
+            (Self::Ssrn as fn(String) -> Self, &ssrn::RE),
+            (
+                Self::ResearchSquare as fn(String) -> Self,
+                &research_square::RE,
+            ),
+            (Self::Chemrxiv as fn(String) -> Self, &chemrxiv::RE),
+            (Self::Preprints as fn(String) -> Self, &preprints::RE),
+            (Self::Zenodo as fn(String) -> Self, &zenodo::RE),
+            (Self::Eartharxiv as fn(String) -> Self, &eartharxiv::RE),
         ] {
             if let Some(id) = cap_as_str(re, &value, 1) {
                 return t(id.to_string());
@@ -28,20 +53,287 @@ impl From<String> for Source {
     }
 }
 
+impl Source {
+    /// The name of the server the DOI was registered by, or "other" for everything else
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Arxiv(_) => "arXiv",
+            Self::Biorxiv(id) => id.server.name(),
+            Self::Osf(_) => "OSF",
+            Self::Ssrn(_) => "SSRN",
+            Self::ResearchSquare(_) => "Research Square",
+            Self::Chemrxiv(_) => "ChemRxiv",
+            Self::Preprints(_) => "Preprints.org",
+            Self::Zenodo(_) => "Zenodo",
+            Self::Eartharxiv(_) => "EarthArXiv",
+            Self::Other(_) => "other",
+        }
+    }
+}
+
 impl From<&str> for Source {
     fn from(value: &str) -> Self {
         value.to_string().into()
     }
 }
 
+impl From<&Doi> for Source {
+    fn from(value: &Doi) -> Self {
+        value.as_str().into()
+    }
+}
+
+/// How far along you are in reading a reference
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadingStatus {
+    Unread,
+    Reading,
+    Read,
+    Skimmed,
+}
+
+impl fmt::Display for ReadingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self
+            .to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .to_string();
+        f.write_str(&s)
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Debug)]
 pub struct EntryData {
     #[serde(default, rename = "picoref_tags")]
     pub tags: Vec<String>,
+    #[serde(
+        default,
+        rename = "picoref_status",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status: Option<ReadingStatus>,
+    /// From 1 to 5
+    #[serde(
+        default,
+        rename = "picoref_rating",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rating: Option<u8>,
+    #[serde(
+        default,
+        rename = "picoref_attachments",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub attachments: Vec<Attachment>,
+    /// The metadata provider that the entry's metadata was fetched from
+    #[serde(
+        default,
+        rename = "picoref_provider",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub provider: Option<String>,
+    /// When the entry was added to the library
+    #[serde(
+        default,
+        rename = "picoref_added",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub added: Option<DateTime<Utc>>,
+    /// When the entry was last written
+    #[serde(
+        default,
+        rename = "picoref_modified",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub modified: Option<DateTime<Utc>>,
     #[serde(flatten)]
     pub fields: Map<String, serde_json::Value>,
 }
 
+/// Item types defined by CSL 1.0.2
+/// See https://docs.citationstyles.org/en/stable/specification.html#appendix-iii-types
+pub const CSL_TYPES: [&str; 45] = [
+    "article",
+    "article-journal",
+    "article-magazine",
+    "article-newspaper",
+    "bill",
+    "book",
+    "broadcast",
+    "chapter",
+    "classic",
+    "collection",
+    "dataset",
+    "document",
+    "entry",
+    "entry-dictionary",
+    "entry-encyclopedia",
+    "event",
+    "figure",
+    "graphic",
+    "hearing",
+    "interview",
+    "legal_case",
+    "legislation",
+    "manuscript",
+    "map",
+    "motion_picture",
+    "musical_score",
+    "pamphlet",
+    "paper-conference",
+    "patent",
+    "performance",
+    "periodical",
+    "personal_communication",
+    "post",
+    "post-weblog",
+    "regulation",
+    "report",
+    "review",
+    "review-book",
+    "software",
+    "song",
+    "speech",
+    "standard",
+    "thesis",
+    "treaty",
+    "webpage",
+];
+
+const NAME_FIELDS: [&str; 8] = [
+    "author",
+    "editor",
+    "translator",
+    "container-author",
+    "collection-editor",
+    "composer",
+    "director",
+    "interviewer",
+];
+
+const DATE_FIELDS: [&str; 5] = [
+    "issued",
+    "accessed",
+    "original-date",
+    "event-date",
+    "submitted",
+];
+
+impl EntryData {
+    /// The year the entry was issued (published)
+    pub fn year(&self) -> Option<u64> {
+        self.fields
+            .get("issued")?
+            .get("date-parts")?
+            .get(0)?
+            .get(0)?
+            .as_u64()
+    }
+
+    /// Carries over everything picoref manages (rather than fetches) from a previous version of
+    /// the entry, e.g. when its metadata is replaced with that of the published version
+    pub fn keep_managed_fields(&mut self, previous: EntryData) {
+        // Destructured so that new fields have to be considered here
+        let EntryData {
+            tags,
+            status,
+            rating,
+            attachments,
+            provider: _,
+            added,
+            modified: _,
+            fields: _,
+        } = previous;
+        self.tags = tags;
+        self.status = status;
+        self.rating = rating;
+        self.attachments = attachments;
+        self.added = added;
+    }
+
+    /// Checks that the entry is valid CSL (as far as picoref relies on it),
+    /// returning a description of every problem that was found.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        match self.fields.get("type").map(|t| t.as_str()) {
+            None => problems.push("missing `type`".to_string()),
+            Some(Some(t)) if CSL_TYPES.contains(&t) => {}
+            Some(t) => problems.push(format!("`type` is not a CSL item type: {t:?}")),
+        }
+        for field in ["id", "citation-key", "title", "DOI", "URL"] {
+            if matches!(self.fields.get(field), Some(v) if !v.is_string()) {
+                problems.push(format!("`{field}` should be a string"));
+            }
+        }
+        if let Some(Err(e)) = self
+            .fields
+            .get("DOI")
+            .and_then(|d| d.as_str())
+            .map(Doi::parse)
+        {
+            problems.push(format!("`DOI` is invalid: {e}"));
+        }
+        // TOML has no null, so entries containing one could not be saved
+        for (field, value) in &self.fields {
+            if contains_null(value) {
+                problems.push(format!("`{field}` should not contain null"));
+            }
+        }
+        if self.rating.is_some_and(|r| !(1..=5).contains(&r)) {
+            problems.push("`picoref_rating` should be from 1 to 5".to_string());
+        }
+        for field in NAME_FIELDS {
+            let Some(value) = self.fields.get(field) else {
+                continue;
+            };
+            if !value
+                .as_array()
+                .is_some_and(|names| names.iter().all(|n| n.is_object()))
+            {
+                problems.push(format!("`{field}` should be a list of names"));
+            }
+        }
+        for field in DATE_FIELDS {
+            let Some(value) = self.fields.get(field) else {
+                continue;
+            };
+            let valid_parts = value.get("date-parts").map(|parts| {
+                parts.as_array().is_some_and(|parts| {
+                    parts
+                        .iter()
+                        .all(|p| p.as_array().is_some_and(|p| !p.is_empty() && p.len() <= 3))
+                })
+            });
+            let has_literal = value.get("literal").or_else(|| value.get("raw")).is_some();
+            match valid_parts {
+                Some(false) => problems.push(format!(
+                    "`{field}.date-parts` should be a list of [year, month, day] lists"
+                )),
+                None if !has_literal => problems.push(format!(
+                    "`{field}` should have `date-parts`, `literal`, or `raw`"
+                )),
+                _ => {}
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!("Invalid CSL:\n  {}", problems.join("\n  "))
+        }
+    }
+}
+
+fn contains_null(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(values) => values.iter().any(contains_null),
+        Value::Object(map) => map.values().any(contains_null),
+        _ => false,
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(try_from = "EntryData")]
 pub struct Entry {
@@ -56,7 +348,63 @@ impl TryFrom<EntryData> for Entry {
             .fields
             .get("DOI")
             .and_then(|doi| doi.as_str())
-            .map(|s| s.into());
+            .and_then(|s| Doi::parse(s).ok())
+            .map(|doi| Source::from(&doi));
         Ok(Self { source, data })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_managed_fields() {
+        let previous = EntryData {
+            tags: vec!["thesis".to_string()],
+            status: Some(ReadingStatus::Reading),
+            rating: Some(4),
+            provider: Some("crossref".to_string()),
+            added: Some(DateTime::UNIX_EPOCH),
+            fields: serde_json::from_str(r#"{"title": "Preprint"}"#).unwrap(),
+            ..Default::default()
+        };
+        let mut data = EntryData {
+            provider: Some("openalex".to_string()),
+            fields: serde_json::from_str(r#"{"title": "Published"}"#).unwrap(),
+            ..Default::default()
+        };
+        data.keep_managed_fields(previous);
+        assert_eq!(data.tags, ["thesis"]);
+        assert_eq!(data.status, Some(ReadingStatus::Reading));
+        assert_eq!(data.rating, Some(4));
+        assert_eq!(data.added, Some(DateTime::UNIX_EPOCH));
+        assert_eq!(data.provider.as_deref(), Some("openalex"));
+        assert_eq!(data.fields["title"], "Published");
+    }
+
+    #[test]
+    fn preprint_servers() {
+        assert_eq!(Source::from("10.1101/2020.03.22.002386").name(), "bioRxiv");
+        assert_eq!(Source::from("10.1101/2020.02.11.20022053").name(), "medRxiv");
+        assert_eq!(Source::from("10.1101/gr.275303.121").name(), "other");
+    }
+
+    #[test]
+    fn rejects_null() {
+        let mut data = EntryData {
+            fields: serde_json::from_str(
+                r#"{"type": "article", "title": "T", "author": [{"family": "Doe", "given": null}]}"#,
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+        let err = data.validate().unwrap_err().to_string();
+        assert!(err.contains("`author` should not contain null"), "{err}");
+        data.fields["author"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("given");
+        data.validate().unwrap();
+    }
+}
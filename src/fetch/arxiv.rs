@@ -1,26 +1,300 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use roxmltree::{Document, Node};
+use serde_json::{Map, Value};
 
+use super::{csl_date, csl_name, MetadataProvider};
+use crate::entry::EntryData;
+use crate::http;
 use crate::regex::cap_as_str;
 
 pub static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)10.48550/arxiv\.(.+)").unwrap());
-static RESP_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)<arxiv:doi.*?>(.+?)</arxiv:doi>").unwrap());
+static VERSION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.+?)(?:v(\d+))?$").unwrap());
+
+const ATOM: &str = "http://www.w3.org/2005/Atom";
+const ARXIV: &str = "http://arxiv.org/schemas/atom";
+
+/// Splits an arXiv identifier (e.g. "2101.00001v2") into the paper's id and its version
+fn split_version(arxiv_id: &str) -> (&str, Option<u32>) {
+    let arxiv_id = arxiv_id.trim();
+    match VERSION_RE.captures(arxiv_id) {
+        Some(caps) => (
+            caps.get(1).map_or(arxiv_id, |m| m.as_str()),
+            caps.get(2).and_then(|m| m.as_str().parse().ok()),
+        ),
+        None => (arxiv_id, None),
+    }
+}
+
+/// A paper as described by the arXiv API
+/// See https://info.arxiv.org/help/api/user-manual.html#_details_of_atom_results_returned
+#[derive(Debug)]
+pub struct Paper {
+    pub id: String,
+    pub version: Option<u32>,
+    pub title: String,
+    pub summary: Option<String>,
+    pub authors: Vec<String>,
+    /// When the first version was submitted
+    pub published: Option<String>,
+    /// When the returned version was submitted
+    pub updated: Option<String>,
+    pub doi: Option<String>,
+    pub journal_ref: Option<String>,
+    pub comment: Option<String>,
+    pub primary_category: Option<String>,
+    pub categories: Vec<String>,
+}
+
+/// arXiv hard-wraps long titles and abstracts
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn child_text(node: Node, namespace: &str, name: &str) -> Option<String> {
+    node.children()
+        .find(|n| n.has_tag_name((namespace, name)))
+        .and_then(|n| n.text())
+        .map(collapse_whitespace)
+        .filter(|s| !s.is_empty())
+}
+
+impl Paper {
+    fn from_atom(xml: &str) -> Result<Self> {
+        let doc = Document::parse(xml).context("Invalid response from arXiv")?;
+        let entry = doc
+            .root_element()
+            .children()
+            .find(|n| n.has_tag_name((ATOM, "entry")))
+            .context("arXiv returned no results")?;
+        // Unknown ids return an entry that only contains an error message
+        let abs_url = child_text(entry, ATOM, "id").context("arXiv returned no results")?;
+        let title = child_text(entry, ATOM, "title").unwrap_or_default();
+        if abs_url.starts_with("http://arxiv.org/api/errors") {
+            anyhow::bail!(
+                "arXiv returned an error: {}",
+                child_text(entry, ATOM, "summary").unwrap_or(title)
+            );
+        }
+        let (id, version) = split_version(
+            abs_url
+                .split_once("/abs/")
+                .map_or(abs_url.as_str(), |(_, id)| id),
+        );
+
+        Ok(Self {
+            id: id.to_string(),
+            version,
+            title,
+            summary: child_text(entry, ATOM, "summary"),
+            authors: entry
+                .children()
+                .filter(|n| n.has_tag_name((ATOM, "author")))
+                .filter_map(|n| child_text(n, ATOM, "name"))
+                .collect(),
+            published: child_text(entry, ATOM, "published"),
+            updated: child_text(entry, ATOM, "updated"),
+            doi: child_text(entry, ARXIV, "doi"),
+            journal_ref: child_text(entry, ARXIV, "journal_ref"),
+            comment: child_text(entry, ARXIV, "comment"),
+            primary_category: entry
+                .children()
+                .find(|n| n.has_tag_name((ARXIV, "primary_category")))
+                .and_then(|n| n.attribute("term"))
+                .map(|s| s.to_string()),
+            categories: entry
+                .children()
+                .filter(|n| n.has_tag_name((ATOM, "category")))
+                .filter_map(|n| n.attribute("term"))
+                .map(|s| s.to_string())
+                .collect(),
+        })
+    }
+
+    /// The paper's identifier, including the version if known (e.g. "2101.00001v2")
+    pub fn versioned_id(&self) -> String {
+        match self.version {
+            Some(v) => format!("{}v{v}", self.id),
+            None => self.id.clone(),
+        }
+    }
+
+    pub fn to_csl(&self) -> EntryData {
+        let mut fields = Map::new();
+        let mut insert = |field: &str, value: Value| {
+            fields.insert(field.to_string(), value);
+        };
+        insert("type", "article".into());
+        insert("title", self.title.clone().into());
+        insert("author", self.authors.iter().map(|a| csl_name(a)).collect());
+        if let Some(summary) = &self.summary {
+            insert("abstract", summary.clone().into());
+        }
+        if let Some(issued) = self.published.as_deref().and_then(csl_date) {
+            insert("issued", issued);
+        }
+        if let Some(available) = self.updated.as_deref().and_then(csl_date) {
+            insert("available-date", available);
+        }
+        insert("DOI", format!("10.48550/arXiv.{}", self.id).into());
+        insert(
+            "URL",
+            format!("https://arxiv.org/abs/{}", self.versioned_id()).into(),
+        );
+        insert("publisher", "arXiv".into());
+        insert("archive", "arXiv".into());
+        insert("archive_location", self.versioned_id().into());
+        if let Some(version) = self.version {
+            insert("version", version.to_string().into());
+        }
+        if let Some(primary) = &self.primary_category {
+            insert("archive_collection", primary.clone().into());
+        }
+        if !self.categories.is_empty() {
+            insert("keyword", self.categories.join(", ").into());
+        }
+        let note: Vec<String> = [
+            self.comment.as_ref().map(|c| format!("Comments: {c}")),
+            self.journal_ref
+                .as_ref()
+                .map(|j| format!("Journal reference: {j}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !note.is_empty() {
+            insert("note", note.join("\n").into());
+        }
+        EntryData {
+            fields,
+            ..Default::default()
+        }
+    }
+}
+
+/// Fetches a paper from the arXiv API. Includes a version suffix to fetch a specific version.
+pub fn fetch(arxiv_id: &str) -> Result<Paper> {
+    let response = http::get("http://export.arxiv.org/api/query")
+        .query("id_list", arxiv_id)
+        .set("Accept", "application/xml; charset=utf-8")
+        .cached()
+        .call()?
+        .into_string()?;
+    Paper::from_atom(&response)
+}
 
 pub fn published_doi(arxiv_id: &str) -> Result<Option<String>> {
-    let response = ureq::get(&format!(
-        "http://export.arxiv.org/api/query?id_list={arxiv_id}"
-    ))
-    .set("Accept", "application/xml; charset=utf-8")
-    .call()?
-    .into_string()?;
-    // HACK: Using a regex here to avoid pulling in an XML parser dependency
-    // this should be replaced with proper parsing if we add such a dependency
-    // in the future.
-    Ok(cap_as_str(&RESP_RE, &response, 1).map(|s| s.trim().to_string()))
-}
-
-pub fn pdf_url(arxiv_id: &str) -> String {
-    format!("https://arxiv.org/pdf/{arxiv_id}")
+    Ok(fetch(arxiv_id)?.doi)
+}
+
+/// The version of the arXiv paper that an entry refers to, if it was fetched from arXiv
+pub fn version(data: &EntryData) -> Option<u32> {
+    if data.fields.get("archive")?.as_str()? != "arXiv" {
+        return None;
+    }
+    split_version(data.fields.get("archive_location")?.as_str()?).1
+}
+
+/// The URL of the PDF for a paper, which is the latest version unless `version` is provided
+pub fn pdf_url(arxiv_id: &str, version: Option<u32>) -> String {
+    let (id, id_version) = split_version(arxiv_id);
+    match version.or(id_version) {
+        Some(v) => format!("https://arxiv.org/pdf/{id}v{v}"),
+        None => format!("https://arxiv.org/pdf/{id}"),
+    }
+}
+
+/// Builds metadata for arXiv DOIs from the arXiv API
+pub struct Arxiv;
+
+impl MetadataProvider for Arxiv {
+    fn name(&self) -> &'static str {
+        "arxiv"
+    }
+
+    fn metadata(&self, doi: &str) -> Result<EntryData> {
+        let id = cap_as_str(&RE, doi, 1).context("not an arXiv DOI")?;
+        Ok(fetch(id)?.to_csl())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_doi_from_arxiv_doi() {
+        assert_eq!(
+            published_doi("1207.7214").unwrap().as_deref(),
+            Some("10.1016/j.physletb.2012.08.020")
+        );
+    }
+
+    #[test]
+    fn no_published_doi() {
+        assert_eq!(published_doi("1706.03762").unwrap(), None);
+    }
+
+    #[test]
+    fn metadata() {
+        let data = Arxiv.metadata("10.48550/arXiv.1706.03762").unwrap();
+        data.validate().unwrap();
+        let fields = &data.fields;
+        assert_eq!(fields["type"], "article");
+        assert_eq!(fields["title"], "Attention Is All You Need");
+        assert_eq!(fields["author"].as_array().unwrap().len(), 8);
+        assert_eq!(fields["author"][5]["family"], "Gomez");
+        assert_eq!(fields["author"][5]["given"], "Aidan N.");
+        assert_eq!(fields["issued"]["date-parts"][0][0], 2017);
+        assert_eq!(fields["archive_location"], "1706.03762v7");
+        assert_eq!(fields["archive_collection"], "cs.CL");
+        assert_eq!(fields["version"], "7");
+        assert_eq!(fields["note"], "Comments: 15 pages, 5 figures");
+        assert_eq!(version(&data), Some(7));
+    }
+
+    #[test]
+    fn specific_version() {
+        let paper = fetch("1706.03762v1").unwrap();
+        assert_eq!(paper.id, "1706.03762");
+        assert_eq!(paper.version, Some(1));
+    }
+
+    #[test]
+    fn unwraps_long_titles() {
+        let paper = fetch("1207.7214").unwrap();
+        assert!(paper.title.starts_with("Observation of a new particle in the search for the Standard Model Higgs boson with the ATLAS"));
+        assert_eq!(paper.authors, ["ATLAS Collaboration"]);
+    }
+
+    #[test]
+    fn keeps_collaborations_whole() {
+        let data = fetch("1207.7214").unwrap().to_csl();
+        assert_eq!(
+            data.fields["author"],
+            serde_json::json!([{ "literal": "ATLAS Collaboration" }])
+        );
+        let data = fetch("1706.03762").unwrap().to_csl();
+        assert_eq!(
+            data.fields["author"][0],
+            serde_json::json!({ "family": "Vaswani", "given": "Ashish" })
+        );
+    }
+
+    #[test]
+    fn versioned_pdf_url() {
+        assert_eq!(
+            pdf_url("1706.03762", None),
+            "https://arxiv.org/pdf/1706.03762"
+        );
+        assert_eq!(
+            pdf_url("1706.03762v2", None),
+            "https://arxiv.org/pdf/1706.03762v2"
+        );
+        assert_eq!(
+            pdf_url("1706.03762v2", Some(3)),
+            "https://arxiv.org/pdf/1706.03762v3"
+        );
+    }
 }
@@ -1,33 +1,365 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
 
-pub static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)(10.1101/.+)").unwrap());
+use super::{csl_date, MetadataProvider};
+use crate::entry::EntryData;
+use crate::http;
+use crate::regex::cap_as_str;
 
-pub fn published_doi(biorxiv_id: &str) -> Result<Option<String>> {
-    fn try_fetch(url: &str) -> Result<Option<String>> {
-        Ok(ureq::get(url)
-            .set("Accept", "application/json; charset=utf-8")
-            .call()?
-            .into_json::<serde_json::Value>()?
-            .get("collection")
-            .and_then(|collection| collection.get(0))
-            .and_then(|metadata| metadata.get("published_doi"))
-            .and_then(|value| value.as_str())
-            .map(|s| s.to_string()))
+/// Preprint DOIs (e.g. "10.1101/2020.01.01.000001" or the older "10.1101/000001"), rather than
+/// those of Cold Spring Harbor Laboratory's journals, which share the prefix
+pub static RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(10\.1101/(?:\d{4}\.\d{2}\.\d{2}\.\d{6,8}|\d{6})(?:v\d+)?)$").unwrap()
+});
+static VERSION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.+?)(?:v(\d+))?$").unwrap());
+/// medRxiv ids end in 8 digits (the year followed by a serial number), bioRxiv ids in 6
+static MEDRXIV_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^10.1101/\d{4}\.\d{2}\.\d{2}\.\d{8}$").unwrap());
+
+/// The two preprint servers run by Cold Spring Harbor Laboratory, which share the `10.1101` prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Server {
+    Biorxiv,
+    Medrxiv,
+}
+
+impl Server {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Biorxiv => "bioRxiv",
+            Self::Medrxiv => "medRxiv",
+        }
+    }
+
+    fn api_name(self) -> &'static str {
+        match self {
+            Self::Biorxiv => "biorxiv",
+            Self::Medrxiv => "medrxiv",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [Self::Biorxiv, Self::Medrxiv]
+            .into_iter()
+            .find(|s| s.api_name().eq_ignore_ascii_case(name))
+    }
+
+    /// The servers in the order they should be tried for a DOI
+    fn candidates(doi: &str) -> [Self; 2] {
+        if MEDRXIV_RE.is_match(doi) {
+            [Self::Medrxiv, Self::Biorxiv]
+        } else {
+            [Self::Biorxiv, Self::Medrxiv]
+        }
+    }
+}
+
+/// Splits a bioRxiv identifier (e.g. "10.1101/2020.01.01.000001v2") into its DOI and version
+fn split_version(biorxiv_id: &str) -> (&str, Option<u32>) {
+    let biorxiv_id = biorxiv_id.trim();
+    match VERSION_RE.captures(biorxiv_id) {
+        Some(caps) => (
+            caps.get(1).map_or(biorxiv_id, |m| m.as_str()),
+            caps.get(2).and_then(|m| m.as_str().parse().ok()),
+        ),
+        None => (biorxiv_id, None),
+    }
+}
+
+/// A bioRxiv or medRxiv preprint, as identified by its DOI (e.g. "10.1101/2020.01.01.000001v2")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprintId {
+    /// The DOI without the version
+    pub doi: String,
+    /// The server the DOI suggests (the preprint may turn out to be on the other one)
+    pub server: Server,
+    pub version: Option<u32>,
+}
+
+impl PreprintId {
+    pub fn parse(id: &str) -> Option<Self> {
+        let (doi, version) = split_version(cap_as_str(&RE, id.trim(), 1)?);
+        Some(Self {
+            doi: doi.to_string(),
+            server: Server::candidates(doi)[0],
+            version,
+        })
+    }
+}
+
+/// A response from the `details` endpoint, which lists every version of a preprint
+/// See https://api.biorxiv.org/
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    collection: Vec<Details>,
+}
+
+#[derive(Deserialize)]
+struct Details {
+    doi: String,
+    title: String,
+    /// Semicolon separated "Family, Given" names
+    authors: String,
+    date: String,
+    version: String,
+    #[serde(rename = "type")]
+    type_: Option<String>,
+    license: Option<String>,
+    category: Option<String>,
+    #[serde(rename = "abstract")]
+    abstract_: Option<String>,
+    /// The DOI of the published version, or "NA"
+    published: Option<String>,
+    server: Option<String>,
+}
+
+/// One version of a bioRxiv or medRxiv preprint
+pub struct Preprint {
+    pub server: Server,
+    pub version: u32,
+    details: Details,
+}
+
+/// Fetches a preprint from whichever server it was posted to.
+/// Returns the latest version unless the id has a version.
+pub fn fetch(id: &PreprintId) -> Result<Preprint> {
+    let PreprintId { doi, version, .. } = id;
+    for server in Server::candidates(doi) {
+        let versions = http::get(&format!(
+            "https://api.biorxiv.org/details/{}/{doi}/na/json",
+            server.api_name()
+        ))
+        .set("Accept", "application/json; charset=utf-8")
+        .cached()
+        .call()?
+        .into_json::<Response>()?
+        .collection;
+        let details = match version {
+            Some(v) => versions.into_iter().find(|d| d.version.parse() == Ok(*v)),
+            None => versions
+                .into_iter()
+                .max_by_key(|d| d.version.parse::<u32>().ok()),
+        };
+        // The preprint (or version) may be on the other server
+        let Some(details) = details else {
+            continue;
+        };
+        return Ok(Preprint {
+            server: details
+                .server
+                .as_deref()
+                .and_then(Server::from_name)
+                .unwrap_or(server),
+            version: details.version.parse().unwrap_or(1),
+            details,
+        });
+    }
+    match version {
+        Some(v) => anyhow::bail!("Version {v} of {doi} was not found on bioRxiv or medRxiv"),
+        None => anyhow::bail!("{doi} was not found on bioRxiv or medRxiv"),
+    }
+}
+
+impl Preprint {
+    fn versioned_id(&self) -> String {
+        format!("{}v{}", self.details.doi, self.version)
+    }
+
+    pub fn to_csl(&self) -> EntryData {
+        let details = &self.details;
+        let mut fields = Map::new();
+        let mut insert = |field: &str, value: Value| {
+            fields.insert(field.to_string(), value);
+        };
+        insert("type", "article".into());
+        insert("title", details.title.trim().into());
+        let authors: Vec<Value> = details
+            .authors
+            .split(';')
+            .map(|a| a.trim())
+            .filter(|a| !a.is_empty())
+            .map(|a| match a.split_once(',') {
+                Some((family, given)) => json!({ "family": family.trim(), "given": given.trim() }),
+                None => json!({ "literal": a }),
+            })
+            .collect();
+        insert("author", authors.into());
+        if let Some(issued) = csl_date(&details.date) {
+            insert("issued", issued);
+        }
+        if let Some(abs) = details.abstract_.as_deref().filter(|a| !a.is_empty()) {
+            insert("abstract", abs.trim().into());
+        }
+        insert("DOI", details.doi.clone().into());
+        insert(
+            "URL",
+            format!(
+                "https://www.{}.org/content/{}",
+                self.server.api_name(),
+                self.versioned_id()
+            )
+            .into(),
+        );
+        insert("publisher", self.server.name().into());
+        insert("archive", self.server.name().into());
+        insert("archive_location", self.versioned_id().into());
+        insert("version", self.version.to_string().into());
+        if let Some(category) = details.category.as_deref().filter(|c| !c.is_empty()) {
+            insert("archive_collection", category.into());
+            insert("keyword", category.into());
+        }
+        if let Some(genre) = details.type_.as_deref().filter(|t| !t.is_empty()) {
+            insert("genre", genre.into());
+        }
+        if let Some(license) = details.license.as_deref().filter(|l| !l.is_empty()) {
+            insert("license", license.into());
+        }
+        EntryData {
+            fields,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn published_doi(id: &PreprintId) -> Result<Option<String>> {
+    Ok(fetch(id)?
+        .details
+        .published
+        .filter(|doi| !doi.is_empty() && doi != "NA"))
+}
+
+/// The server and version that an entry refers to, if its metadata came from bioRxiv or medRxiv
+fn stored_version(data: &EntryData) -> Option<(Server, &str, Option<u32>)> {
+    let server = match data.fields.get("archive")?.as_str()? {
+        "bioRxiv" => Server::Biorxiv,
+        "medRxiv" => Server::Medrxiv,
+        _ => return None,
+    };
+    let (doi, version) = split_version(data.fields.get("archive_location")?.as_str()?);
+    Some((server, doi, version))
+}
+
+/// The URL of the PDF for the version of the preprint stored in the entry (if any),
+/// otherwise of the version in `id`, otherwise of the latest version.
+pub fn pdf_url(id: &PreprintId, data: &EntryData) -> Result<String> {
+    let (server, version) = match stored_version(data) {
+        Some((server, stored_doi, Some(version))) if stored_doi.eq_ignore_ascii_case(&id.doi) => {
+            (server, version)
+        }
+        _ => {
+            let preprint = fetch(id)?;
+            (preprint.server, id.version.unwrap_or(preprint.version))
+        }
+    };
+    Ok(format!(
+        "https://www.{}.org/content/{}v{version}.full.pdf",
+        server.api_name(),
+        id.doi
+    ))
+}
+
+/// Builds metadata for bioRxiv and medRxiv DOIs from their API
+pub struct Biorxiv;
+
+impl MetadataProvider for Biorxiv {
+    fn name(&self) -> &'static str {
+        "biorxiv"
     }
 
-    let mut result = try_fetch(&format!(
-        "https://api.biorxiv.org/pubs/biorxiv/{biorxiv_id}"
-    ))?;
-    if result.is_none() {
-        result = try_fetch(&format!(
-            "https://api.biorxiv.org/pubs/medrxiv/{biorxiv_id}"
-        ))?;
+    fn metadata(&self, doi: &str) -> Result<EntryData> {
+        let id = PreprintId::parse(doi).context("not a bioRxiv or medRxiv DOI")?;
+        Ok(fetch(&id)?.to_csl())
     }
-    Ok(result)
 }
 
-pub fn pdf_url(biorxiv_id: &str) -> String {
-    format!("https://www.biorxiv.org/content/{biorxiv_id}.full.pdf")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(id: &str) -> PreprintId {
+        PreprintId::parse(id).unwrap()
+    }
+
+    #[test]
+    fn parses_ids() {
+        assert_eq!(
+            id("10.1101/2020.02.11.20022053v3"),
+            PreprintId {
+                doi: "10.1101/2020.02.11.20022053".to_string(),
+                server: Server::Medrxiv,
+                version: Some(3),
+            }
+        );
+        assert_eq!(id("10.1101/2020.03.22.002386").server, Server::Biorxiv);
+        assert_eq!(id("10.1101/123456").version, None);
+        // Cold Spring Harbor Laboratory journals
+        assert_eq!(PreprintId::parse("10.1101/gr.275303.121"), None);
+        assert_eq!(PreprintId::parse("10.1101/gad.1234567"), None);
+    }
+
+    #[test]
+    fn published_doi_from_biorxiv() {
+        assert_eq!(
+            published_doi(&id("10.1101/2020.03.22.002386"))
+                .unwrap()
+                .as_deref(),
+            Some("10.1038/s41586-020-2286-9")
+        );
+    }
+
+    #[test]
+    fn published_doi_from_medrxiv() {
+        assert_eq!(
+            published_doi(&id("10.1101/2020.02.11.20022053"))
+                .unwrap()
+                .as_deref(),
+            Some("10.1016/j.ebiom.2020.102763")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_other_server() {
+        // Old bioRxiv-style ids are tried on bioRxiv first
+        let preprint = fetch(&id("10.1101/123456")).unwrap();
+        assert_eq!(preprint.server, Server::Medrxiv);
+        let preprint = fetch(&id("10.1101/123456v1")).unwrap();
+        assert_eq!(preprint.server, Server::Medrxiv);
+        assert_eq!(preprint.version, 1);
+        assert!(fetch(&id("10.1101/123456v2")).is_err());
+    }
+
+    #[test]
+    fn pdf_urls() {
+        let data = EntryData::default();
+        assert_eq!(
+            pdf_url(&id("10.1101/2020.03.22.002386"), &data).unwrap(),
+            "https://www.biorxiv.org/content/10.1101/2020.03.22.002386v2.full.pdf"
+        );
+        assert_eq!(
+            pdf_url(&id("10.1101/2020.03.22.002386v1"), &data).unwrap(),
+            "https://www.biorxiv.org/content/10.1101/2020.03.22.002386v1.full.pdf"
+        );
+        assert_eq!(
+            pdf_url(&id("10.1101/2020.02.11.20022053"), &data).unwrap(),
+            "https://www.medrxiv.org/content/10.1101/2020.02.11.20022053v1.full.pdf"
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let data = Biorxiv.metadata("10.1101/2020.03.22.002386").unwrap();
+        data.validate().unwrap();
+        let fields = &data.fields;
+        assert_eq!(fields["archive"], "bioRxiv");
+        assert_eq!(fields["archive_location"], "10.1101/2020.03.22.002386v2");
+        assert_eq!(fields["archive_collection"], "systems biology");
+        assert_eq!(fields["version"], "2");
+        assert_eq!(fields["author"][0]["family"], "Gordon");
+        assert_eq!(fields["author"][0]["given"], "D. E.");
+        assert_eq!(fields["issued"]["date-parts"][0][2], 27);
+    }
 }
@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::http;
+use crate::regex::cap_as_str;
+
+pub static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)(10.26434/chemrxiv[-.\w]+)").unwrap());
+pub static URL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)chemrxiv\.org/engage/chemrxiv/article-details/([0-9a-f]{24})").unwrap()
+});
+
+const API: &str = "https://chemrxiv.org/engage/chemrxiv/public-api/v1";
+
+/// An item from the ChemRxiv public API
+/// See https://chemrxiv.org/engage/chemrxiv/public-api/documentation
+#[derive(Deserialize)]
+struct Item {
+    doi: Option<String>,
+    /// The version of record
+    vor: Option<Vor>,
+    asset: Option<Asset>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Vor {
+    vor_doi: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    original: Option<Original>,
+}
+
+#[derive(Deserialize)]
+struct Original {
+    url: Option<String>,
+}
+
+fn item(url: &str) -> Result<Item> {
+    Ok(http::get(url)
+        .set("Accept", "application/json")
+        .cached()
+        .call()?
+        .into_json::<Item>()?)
+}
+
+/// Article pages are identified by an item id rather than by the DOI
+pub fn doi_from_url(url: &str) -> Result<Option<String>> {
+    let Some(id) = cap_as_str(&URL_RE, url, 1) else {
+        return Ok(None);
+    };
+    let doi = item(&format!("{API}/items/{id}"))?
+        .doi
+        .context("The ChemRxiv item has no DOI")?;
+    Ok(Some(doi))
+}
+
+pub fn published_doi(doi: &str) -> Result<Option<String>> {
+    Ok(item(&format!("{API}/items/doi/{doi}"))?
+        .vor
+        .and_then(|vor| vor.vor_doi)
+        .filter(|doi| !doi.is_empty()))
+}
+
+pub fn pdf_url(doi: &str) -> Result<String> {
+    item(&format!("{API}/items/doi/{doi}"))?
+        .asset
+        .and_then(|asset| asset.original)
+        .and_then(|original| original.url)
+        .context("The ChemRxiv item has no PDF")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOI: &str = "10.26434/chemrxiv-2021-abcde";
+
+    #[test]
+    fn doi_from_article_url() {
+        assert_eq!(
+            doi_from_url(
+                "https://chemrxiv.org/engage/chemrxiv/article-details/60c7400000000000000000ab"
+            )
+            .unwrap()
+            .as_deref(),
+            Some(DOI)
+        );
+    }
+
+    #[test]
+    fn published_doi_from_vor() {
+        assert_eq!(
+            published_doi(DOI).unwrap().as_deref(),
+            Some("10.1021/jacs.1c00001")
+        );
+    }
+
+    #[test]
+    fn pdf_url_from_asset() {
+        assert!(pdf_url(DOI).unwrap().ends_with("/original/manuscript.pdf"));
+    }
+}
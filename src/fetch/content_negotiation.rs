@@ -0,0 +1,23 @@
+use anyhow::Result;
+
+use super::MetadataProvider;
+use crate::entry::EntryData;
+use crate::http;
+
+/// Requests CSL JSON directly from the DOI resolver, which forwards the request to the DOI's
+/// registration agency (CrossRef, DataCite, mEDRA, ...).
+pub struct ContentNegotiation;
+
+impl MetadataProvider for ContentNegotiation {
+    fn name(&self) -> &'static str {
+        "doi"
+    }
+
+    fn metadata(&self, doi: &str) -> Result<EntryData> {
+        Ok(http::get(&format!("https://dx.doi.org/{doi}"))
+            .set("Accept", "application/citeproc+json; charset=utf-8")
+            .cached()
+            .call()?
+            .into_json()?)
+    }
+}
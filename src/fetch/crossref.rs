@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use super::MetadataProvider;
+use crate::entry::EntryData;
+use crate::http;
+
+/// Maps CrossRef's work types onto CSL item types
+/// See https://api.crossref.org/types
+pub(super) fn csl_type(crossref_type: &str) -> &'static str {
+    match crossref_type {
+        "journal-article" => "article-journal",
+        "proceedings-article" => "paper-conference",
+        "book-chapter" | "book-section" | "book-part" => "chapter",
+        "book" | "monograph" | "edited-book" | "reference-book" | "book-set" => "book",
+        "book-series" | "book-track" | "journal" | "journal-volume" | "journal-issue" => {
+            "periodical"
+        }
+        "proceedings" | "proceedings-series" => "book",
+        "reference-entry" => "entry",
+        "report" | "report-series" => "report",
+        "dissertation" => "thesis",
+        "dataset" | "database" => "dataset",
+        "standard" | "standard-series" => "standard",
+        "peer-review" => "review",
+        "posted-content" => "article",
+        _ => "document",
+    }
+}
+
+/// Fields that CrossRef returns as lists but CSL expects to be a single string
+const LIST_FIELDS: [&str; 5] = [
+    "title",
+    "container-title",
+    "short-container-title",
+    "original-title",
+    "subtitle",
+];
+
+/// Uses the CrossRef REST API, whose works are close to (but not quite) CSL
+/// See https://api.crossref.org/swagger-ui/index.html
+pub struct Crossref {
+    pub email: String,
+}
+
+impl Crossref {
+    pub fn work(&self, doi: &str) -> Result<Map<String, Value>> {
+        let mut response = http::get(&format!("https://api.crossref.org/works/{doi}"))
+            .query("mailto", &self.email)
+            .cached()
+            .call()?
+            .into_json::<Value>()?;
+        Ok(serde_json::from_value(
+            response
+                .get_mut("message")
+                .context("CrossRef response has no message")?
+                .take(),
+        )?)
+    }
+
+    /// The DOI of the published version of a preprint, from its `is-preprint-of` relation
+    pub fn preprint_of(&self, doi: &str) -> Result<Option<String>> {
+        Ok(is_preprint_of(&self.work(doi)?))
+    }
+
+    /// The DOIs of works that list `doi` as their preprint (with a `has-preprint` relation)
+    pub fn works_with_preprint(&self, doi: &str) -> Result<Vec<String>> {
+        let response = http::get("https://api.crossref.org/works")
+            .query(
+                "filter",
+                &format!("relation.type:has-preprint,relation.object:{doi}"),
+            )
+            .query("mailto", &self.email)
+            .cached()
+            .call()?
+            .into_json::<Value>()?;
+        Ok(response["message"]["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.get("DOI")?.as_str())
+            .map(|doi| doi.to_string())
+            .collect())
+    }
+}
+
+/// The DOI in a work's `is-preprint-of` relation
+pub fn is_preprint_of(work: &Map<String, Value>) -> Option<String> {
+    let relations = work
+        .get("relation")
+        .and_then(|r| r.get("is-preprint-of"))
+        .and_then(|r| r.as_array());
+    relations
+        .into_iter()
+        .flatten()
+        .filter(|r| r.get("id-type").and_then(|t| t.as_str()) == Some("doi"))
+        .find_map(|r| r.get("id")?.as_str())
+        .map(|id| id.trim_start_matches("https://doi.org/").to_string())
+}
+
+impl MetadataProvider for Crossref {
+    fn name(&self) -> &'static str {
+        "crossref"
+    }
+
+    fn metadata(&self, doi: &str) -> Result<EntryData> {
+        let mut fields = self.work(doi)?;
+
+        for field in LIST_FIELDS {
+            if let Some(value) = fields.remove(field) {
+                let first = value.as_array().and_then(|v| v.first()).cloned();
+                if let Some(first) = first.or(Some(value).filter(|v| v.is_string())) {
+                    let name = match field {
+                        "short-container-title" => "container-title-short",
+                        f => f,
+                    };
+                    fields.insert(name.to_string(), first);
+                }
+            }
+        }
+        let crossref_type = fields.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        fields.insert("type".to_string(), csl_type(crossref_type).into());
+        if let Some(date) = ["published-print", "published-online", "issued", "created"]
+            .iter()
+            .find_map(|f| fields.get(*f).filter(|d| d.get("date-parts").is_some()))
+        {
+            fields.insert("issued".to_string(), date.clone());
+        }
+        // Organizations are listed with a `name` rather than a `family` name
+        for field in ["author", "editor", "translator"] {
+            if let Some(Value::Array(names)) = fields.get_mut(field) {
+                for name in names.iter_mut() {
+                    if let Some(n) = name.as_object_mut() {
+                        if let Some(literal) = n.remove("name") {
+                            n.insert("literal".to_string(), literal);
+                        }
+                    }
+                }
+            }
+        }
+        // Bookkeeping fields that are large and not part of CSL
+        for field in [
+            "reference",
+            "relation",
+            "indexed",
+            "deposited",
+            "content-domain",
+        ] {
+            fields.remove(field);
+        }
+
+        Ok(EntryData {
+            fields,
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::{csl_date, MetadataProvider};
+use crate::entry::{EntryData, CSL_TYPES};
+use crate::http;
+
+#[derive(Deserialize)]
+struct Response {
+    data: Data,
+}
+
+#[derive(Deserialize)]
+struct Data {
+    attributes: Attributes,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Attributes {
+    doi: String,
+    url: Option<String>,
+    #[serde(default)]
+    titles: Vec<Title>,
+    #[serde(default)]
+    creators: Vec<Creator>,
+    /// A string in older versions of the API, and an object in newer ones
+    publisher: Option<Value>,
+    publication_year: Option<Value>,
+    #[serde(default)]
+    dates: Vec<DateInfo>,
+    #[serde(default)]
+    descriptions: Vec<Description>,
+    types: Option<Types>,
+    version: Option<String>,
+    language: Option<String>,
+    container: Option<Container>,
+    #[serde(default)]
+    related_identifiers: Vec<RelatedIdentifier>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RelatedIdentifier {
+    related_identifier: String,
+    related_identifier_type: Option<String>,
+    relation_type: String,
+}
+
+#[derive(Deserialize)]
+struct Title {
+    title: String,
+    #[serde(rename = "titleType")]
+    title_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Creator {
+    name: Option<String>,
+    given_name: Option<String>,
+    family_name: Option<String>,
+    name_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DateInfo {
+    date: String,
+    date_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Description {
+    description: Option<String>,
+    description_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Types {
+    citeproc: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Container {
+    title: Option<String>,
+    volume: Option<String>,
+    issue: Option<String>,
+    first_page: Option<String>,
+    last_page: Option<String>,
+}
+
+fn attributes(doi: &str) -> Result<Attributes> {
+    Ok(http::get(&format!("https://api.datacite.org/dois/{doi}"))
+        .set("Accept", "application/vnd.api+json")
+        .cached()
+        .call()?
+        .into_json::<Response>()?
+        .data
+        .attributes)
+}
+
+/// The first DOI that is related to `doi` by one of `relation_types` (e.g. "IsIdenticalTo"),
+/// ignoring DOIs that start with `exclude_prefix`
+pub fn related_doi(
+    doi: &str,
+    relation_types: &[&str],
+    exclude_prefix: &str,
+) -> Result<Option<String>> {
+    let attrs = attributes(doi)?;
+    Ok(relation_types.iter().find_map(|relation_type| {
+        attrs
+            .related_identifiers
+            .iter()
+            .filter(|r| r.relation_type == *relation_type)
+            .filter(|r| r.related_identifier_type.as_deref() == Some("DOI"))
+            .map(|r| r.related_identifier.trim_start_matches("https://doi.org/"))
+            .find(|r| !r.starts_with(exclude_prefix))
+            .map(|r| r.to_string())
+    }))
+}
+
+/// Uses the DataCite REST API (used by e.g. Zenodo, arXiv, figshare, and many data repositories)
+/// See https://support.datacite.org/docs/api-get-doi
+pub struct Datacite;
+
+impl MetadataProvider for Datacite {
+    fn name(&self) -> &'static str {
+        "datacite"
+    }
+
+    fn metadata(&self, doi: &str) -> Result<EntryData> {
+        to_csl(attributes(doi)?)
+    }
+}
+
+fn to_csl(attrs: Attributes) -> Result<EntryData> {
+    let mut fields = Map::new();
+    let csl_type = attrs
+        .types
+        .and_then(|t| t.citeproc)
+        .filter(|t| CSL_TYPES.contains(&t.as_str()))
+        .unwrap_or("document".to_string());
+    fields.insert("type".to_string(), csl_type.into());
+    fields.insert("DOI".to_string(), attrs.doi.into());
+    if let Some(url) = attrs.url {
+        fields.insert("URL".to_string(), url.into());
+    }
+    let title = attrs
+        .titles
+        .iter()
+        .find(|t| t.title_type.is_none())
+        .or(attrs.titles.first())
+        .context("DataCite record has no title")?;
+    fields.insert("title".to_string(), title.title.clone().into());
+
+    let authors: Vec<Value> = attrs
+        .creators
+        .into_iter()
+        .filter_map(|c| match (c.family_name, c.name_type.as_deref()) {
+            (Some(family), Some("Personal") | None) => {
+                let mut name = json!({ "family": family });
+                // Leaving out a missing given name rather than writing null, which TOML lacks
+                if let Some(given) = c.given_name {
+                    name["given"] = given.into();
+                }
+                Some(name)
+            }
+            _ => Some(json!({ "literal": c.name? })),
+        })
+        .collect();
+    fields.insert("author".to_string(), authors.into());
+
+    let publisher = attrs.publisher.and_then(|p| match p {
+        Value::String(s) => Some(s),
+        p => Some(p.get("name")?.as_str()?.to_string()),
+    });
+    if let Some(publisher) = publisher {
+        fields.insert("publisher".to_string(), publisher.into());
+    }
+    let issued = attrs
+        .dates
+        .iter()
+        .find(|d| d.date_type.as_deref() == Some("Issued"))
+        .and_then(|d| csl_date(&d.date))
+        .or_else(|| {
+            let year = attrs.publication_year.as_ref()?;
+            let year = year.as_u64().or_else(|| year.as_str()?.parse().ok())?;
+            Some(json!({ "date-parts": [[year]] }))
+        });
+    if let Some(issued) = issued {
+        fields.insert("issued".to_string(), issued);
+    }
+    if let Some(abs) = attrs
+        .descriptions
+        .into_iter()
+        .find(|d| d.description_type.as_deref() == Some("Abstract"))
+        .and_then(|d| d.description)
+    {
+        fields.insert("abstract".to_string(), abs.into());
+    }
+    if let Some(version) = attrs.version {
+        fields.insert("version".to_string(), version.into());
+    }
+    if let Some(language) = attrs.language {
+        fields.insert("language".to_string(), language.into());
+    }
+    if let Some(container) = attrs.container {
+        for (field, value) in [
+            ("container-title", container.title),
+            ("volume", container.volume),
+            ("issue", container.issue),
+        ] {
+            if let Some(v) = value {
+                fields.insert(field.to_string(), v.into());
+            }
+        }
+        if let Some(first) = container.first_page {
+            let page = match container.last_page {
+                Some(last) => format!("{first}-{last}"),
+                None => first,
+            };
+            fields.insert("page".to_string(), page.into());
+        }
+    }
+
+    Ok(EntryData {
+        fields,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn family_only_creators() {
+        let attrs: Attributes = serde_json::from_value(json!({
+            "doi": "10.5281/zenodo.1234567",
+            "titles": [{"title": "A dataset"}],
+            "creators": [
+                {"name": "Doe", "familyName": "Doe", "nameType": "Personal"},
+                {"name": "Example Lab", "nameType": "Organizational"},
+            ],
+            "types": {"citeproc": "dataset"},
+        }))
+        .unwrap();
+        let data = to_csl(attrs).unwrap();
+        assert_eq!(
+            data.fields["author"],
+            json!([{"family": "Doe"}, {"literal": "Example Lab"}])
+        );
+        data.validate().unwrap();
+        toml::to_string_pretty(&data).unwrap();
+    }
+
+    #[test]
+    fn keeps_csl_types() {
+        let csl_type = |citeproc: &str| {
+            let attrs: Attributes = serde_json::from_value(json!({
+                "doi": "10.48550/arxiv.2101.00001",
+                "titles": [{"title": "A preprint"}],
+                "types": {"citeproc": citeproc},
+            }))
+            .unwrap();
+            to_csl(attrs).unwrap().fields["type"].clone()
+        };
+        assert_eq!(csl_type("article"), "article");
+        assert_eq!(csl_type("dataset"), "dataset");
+        assert_eq!(csl_type("not-a-csl-type"), "document");
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::http;
+use crate::regex::cap_as_str;
+
+pub static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)(10.31223/X5\w+)").unwrap());
+pub static URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)eartharxiv\.org/repository/view/(\d+)").unwrap());
+static META_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<meta\b[^>]*>").unwrap());
+static ATTRIBUTE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([\w-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+/// EarthArXiv has no public API, but its landing pages have Highwire Press `citation_*` tags.
+fn landing_page(url: &str) -> Result<String> {
+    Ok(http::get(url)
+        .set("Accept", "text/html")
+        .cached()
+        .call()?
+        .into_string()?)
+}
+
+/// The content of the `<meta>` tag with the given name, whatever order its attributes are in
+fn meta_content(page: &str, name: &str) -> Option<String> {
+    META_RE.find_iter(page).find_map(|tag| {
+        let mut tag_name = None;
+        let mut content = None;
+        for caps in ATTRIBUTE_RE.captures_iter(tag.as_str()) {
+            let value = caps.get(2).or(caps.get(3)).map_or("", |m| m.as_str());
+            match caps[1].to_lowercase().as_str() {
+                "name" => tag_name = Some(value),
+                "content" => content = Some(value),
+                _ => {}
+            }
+        }
+        tag_name
+            .filter(|n| n.eq_ignore_ascii_case(name))
+            .and(content)
+            .map(|c| c.replace("&amp;", "&"))
+    })
+}
+
+pub fn doi_from_url(url: &str) -> Result<Option<String>> {
+    let Some(id) = cap_as_str(&URL_RE, url, 1) else {
+        return Ok(None);
+    };
+    let page = landing_page(&format!("https://eartharxiv.org/repository/view/{id}/"))?;
+    let doi = meta_content(&page, "citation_doi").context("The EarthArXiv page has no DOI")?;
+    Ok(Some(doi.trim_start_matches("https://doi.org/").to_string()))
+}
+
+pub fn pdf_url(doi: &str) -> Result<String> {
+    let page = landing_page(&format!("https://doi.org/{doi}"))?;
+    meta_content(&page, "citation_pdf_url").context("The EarthArXiv page has no PDF link")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doi_from_landing_page() {
+        assert_eq!(
+            doi_from_url("https://eartharxiv.org/repository/view/1234/")
+                .unwrap()
+                .as_deref(),
+            Some("10.31223/X5AB12")
+        );
+        // Attributes can be in any order, and quoted either way
+        assert_eq!(
+            doi_from_url("https://eartharxiv.org/repository/view/5678/")
+                .unwrap()
+                .as_deref(),
+            Some("10.31223/X5CD34")
+        );
+    }
+
+    #[test]
+    fn pdf_url_from_landing_page() {
+        assert_eq!(
+            pdf_url("10.31223/X5AB12").unwrap(),
+            "https://eartharxiv.org/repository/object/1234/download/2345/"
+        );
+    }
+
+    #[test]
+    fn meta_tags_in_any_order() {
+        let page = r#"<meta property="og:type" content="article">
+            <meta
+                content="https://eartharxiv.org/download/?a=1&amp;b=2"
+                name="citation_pdf_url">"#;
+        assert_eq!(
+            meta_content(page, "citation_pdf_url").as_deref(),
+            Some("https://eartharxiv.org/download/?a=1&b=2")
+        );
+        assert_eq!(meta_content(page, "citation_doi"), None);
+    }
+}
@@ -1,72 +1,313 @@
 pub mod arxiv;
 pub mod biorxiv;
+pub mod chemrxiv;
+pub mod content_negotiation;
+pub mod crossref;
+pub mod datacite;
+pub mod eartharxiv;
 pub mod openalex;
 pub mod osf;
 pub mod pmc;
+pub mod preprints;
+pub mod relations;
+pub mod research_square;
 pub mod semantic_scholar;
+pub mod ssrn;
 pub mod unpaywall;
+pub mod zenodo;
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
+use crate::config::Config;
+use crate::doi::Doi;
 use crate::entry::{Entry, EntryData, Source};
 
+/// Returns the DOI for a preprint server URL (e.g. a Zenodo record), if it is one we recognize
+pub fn doi_from_url(url: &str) -> Result<Option<String>> {
+    let doi = ssrn::doi_from_url(url)
+        .or_else(|| research_square::doi_from_url(url))
+        .or_else(|| preprints::doi_from_url(url))
+        .or_else(|| zenodo::doi_from_url(url));
+    if doi.is_some() {
+        return Ok(doi);
+    }
+    if let Some(doi) = chemrxiv::doi_from_url(url)? {
+        return Ok(Some(doi));
+    }
+    eartharxiv::doi_from_url(url)
+}
+
 /// Checks if there is a published version of this paper (e.g. if the provided DOI is a pre-print)
 /// and returns the DOI of the published version, if available.
-pub fn published_doi(source: &Source) -> Result<Option<String>> {
-    match source {
-        Source::Arxiv(id) => arxiv::published_doi(id),
-        Source::Biorxiv(id) => biorxiv::published_doi(id),
-        Source::Osf(id) => osf::published_doi(id),
+/// Preprint servers' own APIs are checked first, then the relations between works in general.
+pub fn published_doi(doi: &Doi, conf: &Config) -> Result<Option<Doi>> {
+    let crossref = crossref::Crossref {
+        email: conf.email.clone(),
+    };
+    let from_server = match doi.into() {
+        Source::Arxiv(id) => arxiv::published_doi(&id),
+        Source::Biorxiv(id) => biorxiv::published_doi(&id),
+        Source::Osf(id) => osf::published_doi(&id),
+        Source::Chemrxiv(doi) => chemrxiv::published_doi(&doi),
+        Source::Zenodo(doi) => zenodo::published_doi(&doi),
+        // These servers register their DOIs with CrossRef, along with links to published versions
+        Source::Ssrn(doi)
+        | Source::ResearchSquare(doi)
+        | Source::Preprints(doi)
+        | Source::Eartharxiv(doi) => crossref.preprint_of(&doi),
         Source::Other(_) => Ok(None),
+    };
+    let published =
+        or_from_relations(from_server, || relations::published_doi(doi.as_str(), conf))?;
+    published.map(|p| Doi::parse(&p)).transpose()
+}
+
+/// Falls back to the relations between works when the preprint server does not know of a
+/// published version. If the server's lookup failed, its error is kept unless the relations
+/// find a published version, since "not published yet" would be misleading.
+fn or_from_relations(
+    from_server: Result<Option<String>>,
+    from_relations: impl FnOnce() -> Result<Option<String>>,
+) -> Result<Option<String>> {
+    match from_server {
+        Ok(Some(published)) => Ok(Some(published)),
+        Ok(None) => from_relations(),
+        // Prefer the more specific error
+        Err(e) => match from_relations() {
+            Ok(Some(published)) => Ok(Some(published)),
+            Ok(None) | Err(_) => Err(e),
+        },
     }
 }
 
-pub fn fetch_metadata(doi: &str) -> Result<Entry> {
+/// Fetches the metadata for a DOI (or for its published version, if it is a pre-print),
+/// trying each of the configured `metadata_providers` in order.
+pub fn fetch_metadata(doi: &Doi, conf: &Config) -> Result<Entry> {
     let source = doi.into();
-    let url = if let Some(published) = published_doi(&source)? {
-        format!("https://dx.doi.org/{published}")
-    } else {
-        format!("https://dx.doi.org/{doi}")
-    };
-    let data: EntryData =
-        ureq::get(&url)
-            .set("Accept", "application/citeproc+json; charset=utf-8")
-            .call()?
-            .into_json()?;
-    Ok(Entry {
-        source: Some(source),
-        data,
-    })
+    let published = published_doi(doi, conf).unwrap_or_else(|e| {
+        eprintln!("Could not check for a published version of {doi}: {e:#}");
+        None
+    });
+    let doi = published.as_ref().unwrap_or(doi);
+
+    let mut failures = Vec::new();
+    for provider in conf.metadata_providers.iter().map(|kind| kind.build(conf)) {
+        match provider.metadata(doi.as_str()).and_then(|data| {
+            data.validate()?;
+            Ok(data)
+        }) {
+            Ok(mut data) => {
+                data.provider = Some(provider.name().to_string());
+                return Ok(Entry {
+                    source: Some(source),
+                    data,
+                });
+            }
+            Err(e) => failures.push(format!("{}: {e:#}", provider.name())),
+        }
+    }
+    if failures.is_empty() {
+        anyhow::bail!("Could not fetch metadata (no `metadata_providers` are enabled)")
+    }
+    anyhow::bail!(
+        "Could not fetch metadata for {doi}:\n  {}",
+        failures.join("\n  ")
+    )
+}
+
+/// A service that can be queried for the metadata of a DOI
+pub trait MetadataProvider {
+    /// The name used to refer to this provider in the configuration and in error messages
+    fn name(&self) -> &'static str;
+
+    /// Returns the metadata for the DOI as CSL
+    fn metadata(&self, doi: &str) -> Result<EntryData>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataProviderKind {
+    Arxiv,
+    Biorxiv,
+    /// Content negotiation with the DOI resolver
+    Doi,
+    Crossref,
+    Datacite,
+    #[serde(rename = "openalex")]
+    OpenAlex,
+    SemanticScholar,
 }
 
-pub fn fetch_pdf_url(source: &Source, email: &str, title: Option<&str>) -> Result<String> {
+impl MetadataProviderKind {
+    pub const ALL: [Self; 7] = [
+        Self::Arxiv,
+        Self::Biorxiv,
+        Self::Doi,
+        Self::Crossref,
+        Self::Datacite,
+        Self::OpenAlex,
+        Self::SemanticScholar,
+    ];
+
+    fn build(self, conf: &Config) -> Box<dyn MetadataProvider> {
+        match self {
+            Self::Arxiv => Box::new(arxiv::Arxiv),
+            Self::Biorxiv => Box::new(biorxiv::Biorxiv),
+            Self::Doi => Box::new(content_negotiation::ContentNegotiation),
+            Self::Crossref => Box::new(crossref::Crossref {
+                email: conf.email.clone(),
+            }),
+            Self::Datacite => Box::new(datacite::Datacite),
+            Self::OpenAlex => Box::new(openalex::OpenAlex {
+                email: conf.email.clone(),
+                api_key: conf.api_keys.openalex.clone(),
+            }),
+            Self::SemanticScholar => Box::new(semantic_scholar::SemanticScholar {
+                api_key: conf.api_keys.semantic_scholar.clone(),
+            }),
+        }
+    }
+}
+
+/// Parses an ISO 8601 date (or a prefix of one, e.g. "2021-03") into a CSL date
+fn csl_date(date: &str) -> Option<Value> {
+    let parts: Vec<u64> = date
+        .get(..10.min(date.len()))?
+        .split('-')
+        .map_while(|p| p.parse().ok())
+        .collect();
+    (!parts.is_empty()).then(|| json!({ "date-parts": [parts] }))
+}
+
+/// Words that show that a name is that of a group rather than a person (e.g. "ATLAS Collaboration")
+const GROUP_WORDS: [&str; 10] = [
+    "collaboration",
+    "consortium",
+    "group",
+    "team",
+    "committee",
+    "initiative",
+    "network",
+    "project",
+    "society",
+    "association",
+];
+
+/// Splits a display name (e.g. "Ada King Lovelace") into a CSL name.
+/// Only for APIs that do not provide the parts of names, since this is a guess.
+/// The names of groups are kept whole, as literal names.
+fn csl_name(display_name: &str) -> Value {
+    let is_group = display_name
+        .split_whitespace()
+        .any(|word| GROUP_WORDS.contains(&word.to_lowercase().as_str()));
+    if is_group {
+        return json!({ "literal": display_name.trim() });
+    }
+    match display_name.trim().rsplit_once(' ') {
+        Some((given, family)) => json!({ "family": family, "given": given.trim() }),
+        None => json!({ "literal": display_name.trim() }),
+    }
+}
+
+pub fn fetch_pdf_url(source: &Source, conf: &Config, data: &EntryData) -> Result<String> {
+    let title = data.fields.get("title").and_then(|t| t.as_str());
     match source {
-        Source::Arxiv(id) => Ok(arxiv::pdf_url(id)),
-        Source::Biorxiv(id) => Ok(biorxiv::pdf_url(id)),
+        Source::Arxiv(id) => Ok(arxiv::pdf_url(id, arxiv::version(data))),
+        // An open access copy may still be listed when the preprint server's API cannot help
+        Source::Biorxiv(id) => biorxiv::pdf_url(id, data).or_else(|e| {
+            fetch_oa_pdf_url(&id.doi, conf, title)
+                .map_err(|oa| anyhow::anyhow!("{}: {e:#}\n{oa:#}", id.server.name()))
+        }),
         Source::Osf(id) => osf::pdf_url(id),
-        Source::Other(doi) => fetch_oa_pdf_url(doi, email, title),
+        Source::Ssrn(doi) => ssrn::pdf_url(doi),
+        Source::ResearchSquare(doi) => research_square::pdf_url(doi),
+        Source::Chemrxiv(doi) => chemrxiv::pdf_url(doi),
+        Source::Preprints(doi) => preprints::pdf_url(doi),
+        Source::Zenodo(doi) => zenodo::pdf_url(doi),
+        Source::Eartharxiv(doi) => eartharxiv::pdf_url(doi),
+        Source::Other(doi) => fetch_oa_pdf_url(doi, conf, title),
     }
 }
 
-fn fetch_oa_pdf_url(doi: &str, email: &str, title: Option<&str>) -> Result<String> {
-    if let Some(url) = try_source(|| unpaywall::pdf_url(doi, email)) {
-        return Ok(url);
-    }
-    if let Some(url) = try_source(|| semantic_scholar::pdf_url(doi, title)) {
-        return Ok(url);
+/// A service that can be searched for an open access PDF by DOI
+pub trait PdfSource {
+    /// The name used to refer to this source in the configuration and in error messages
+    fn name(&self) -> &'static str;
+
+    /// Returns a URL for a PDF of the paper, or `None` if the source knows of no such PDF
+    fn pdf_url(&self, doi: &str, title: Option<&str>) -> Result<Option<String>>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PdfSourceKind {
+    Unpaywall,
+    SemanticScholar,
+    #[serde(rename = "openalex")]
+    OpenAlex,
+    Pmc,
+}
+
+impl PdfSourceKind {
+    pub const ALL: [Self; 4] = [
+        Self::Unpaywall,
+        Self::SemanticScholar,
+        Self::OpenAlex,
+        Self::Pmc,
+    ];
+
+    fn build(self, conf: &Config) -> Box<dyn PdfSource> {
+        match self {
+            Self::Unpaywall => Box::new(unpaywall::Unpaywall {
+                email: conf.email.clone(),
+            }),
+            Self::SemanticScholar => Box::new(semantic_scholar::SemanticScholar {
+                api_key: conf.api_keys.semantic_scholar.clone(),
+            }),
+            Self::OpenAlex => Box::new(openalex::OpenAlex {
+                email: conf.email.clone(),
+                api_key: conf.api_keys.openalex.clone(),
+            }),
+            Self::Pmc => Box::new(pmc::Pmc),
+        }
     }
-    if let Some(url) = try_source(|| openalex::pdf_url(doi, email)) {
-        return Ok(url);
+}
+
+/// Tries each of the configured `pdf_sources` in order, returning the first PDF found.
+/// If none is found, the error lists what happened with each source.
+fn fetch_oa_pdf_url(doi: &str, conf: &Config, title: Option<&str>) -> Result<String> {
+    let mut failures = Vec::new();
+    for source in conf.pdf_sources.iter().map(|kind| kind.build(conf)) {
+        match source.pdf_url(doi, title) {
+            Ok(Some(url)) => return Ok(url),
+            Ok(None) => failures.push(format!("{}: no open access PDF listed", source.name())),
+            Err(e) => failures.push(format!("{}: {e:#}", source.name())),
+        }
     }
-    if let Some(url) = try_source(|| pmc::pdf_url(doi)) {
-        return Ok(url);
+    if failures.is_empty() {
+        anyhow::bail!("No open access PDF found (no `pdf_sources` are enabled)")
     }
-    anyhow::bail!("No open access PDF found")
+    anyhow::bail!("No open access PDF found:\n  {}", failures.join("\n  "))
 }
 
-fn try_source(fetch: impl FnOnce() -> Result<Option<String>>) -> Option<String> {
-    match fetch() {
-        Ok(Some(url)) => Some(url),
-        _ => None,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_server_errors() {
+        let found = || Ok(Some("10.1/published".to_string()));
+        let failed = || anyhow::bail!("server down");
+        assert_eq!(
+            or_from_relations(failed(), found).unwrap().as_deref(),
+            Some("10.1/published")
+        );
+        let err = or_from_relations(failed(), || Ok(None)).unwrap_err();
+        assert_eq!(err.to_string(), "server down");
+        let err = or_from_relations(failed(), || anyhow::bail!("relations down")).unwrap_err();
+        assert_eq!(err.to_string(), "server down");
+        assert_eq!(or_from_relations(Ok(None), || Ok(None)).unwrap(), None);
     }
 }
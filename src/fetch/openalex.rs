@@ -1,5 +1,13 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::{crossref, csl_date, csl_name, MetadataProvider, PdfSource};
+use crate::entry::EntryData;
+use crate::http;
 
 #[derive(Deserialize)]
 struct Response {
@@ -8,6 +16,21 @@ struct Response {
     locations: Vec<Location>,
 }
 
+/// The parts of a work that describe the versions that OpenAlex has merged into it
+#[derive(Deserialize)]
+struct Versions {
+    doi: Option<String>,
+    type_crossref: Option<String>,
+    #[serde(default)]
+    locations: Vec<VersionLocation>,
+}
+
+#[derive(Deserialize)]
+struct VersionLocation {
+    version: Option<String>,
+    landing_page_url: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct OpenAccess {
     oa_url: Option<String>,
@@ -18,20 +41,212 @@ struct Location {
     pdf_url: Option<String>,
 }
 
-pub fn pdf_url(doi: &str, email: &str) -> Result<Option<String>> {
-    let response = ureq::get(&format!("https://api.openalex.org/works/doi:{doi}"))
-        .query("mailto", email)
-        .call()?
-        .into_json::<Response>()?;
-    let url = response
-        .best_oa_location
-        .and_then(|loc| loc.pdf_url)
-        .or_else(|| {
-            response
-                .locations
-                .into_iter()
-                .find_map(|loc| loc.pdf_url)
+#[derive(Deserialize)]
+struct Work {
+    doi: Option<String>,
+    title: Option<String>,
+    #[serde(default)]
+    authorships: Vec<Authorship>,
+    publication_date: Option<String>,
+    type_crossref: Option<String>,
+    language: Option<String>,
+    primary_location: Option<PrimaryLocation>,
+    biblio: Option<Biblio>,
+    abstract_inverted_index: Option<HashMap<String, Vec<usize>>>,
+}
+
+#[derive(Deserialize)]
+struct Authorship {
+    author: Author,
+}
+
+#[derive(Deserialize)]
+struct Author {
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PrimaryLocation {
+    source: Option<LocationSource>,
+}
+
+#[derive(Deserialize)]
+struct LocationSource {
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Biblio {
+    volume: Option<String>,
+    issue: Option<String>,
+    first_page: Option<String>,
+    last_page: Option<String>,
+}
+
+/// OpenAlex stores abstracts as a map from each word to its positions in the text
+fn reconstruct_abstract(index: HashMap<String, Vec<usize>>) -> String {
+    let mut words: Vec<(usize, String)> = index
+        .into_iter()
+        .flat_map(|(word, positions)| positions.into_iter().map(move |i| (i, word.clone())))
+        .collect();
+    words.sort();
+    words
+        .into_iter()
+        .map(|(_, w)| w)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub struct OpenAlex {
+    pub email: String,
+    pub api_key: Option<String>,
+}
+
+impl OpenAlex {
+    fn work<T: DeserializeOwned>(&self, doi: &str) -> Result<T> {
+        let mut request = http::get(&format!("https://api.openalex.org/works/doi:{doi}"))
+            .query("mailto", &self.email);
+        if let Some(key) = &self.api_key {
+            request = request.secret_query("api_key", key);
+        }
+        Ok(request.cached().call()?.into_json::<T>()?)
+    }
+
+    /// OpenAlex merges the preprint and published versions of a paper into one work, whose DOI
+    /// is usually the published version's. Returns that DOI if it differs from `doi`.
+    pub fn published_doi(&self, doi: &str) -> Result<Option<String>> {
+        let work = self.work::<Versions>(doi)?;
+        let strip = |url: &str| url.trim_start_matches("https://doi.org/").to_string();
+        let is_other = |other: &String| !other.eq_ignore_ascii_case(doi);
+        if work.type_crossref.as_deref() != Some("posted-content") {
+            if let Some(published) = work.doi.as_deref().map(strip).filter(is_other) {
+                return Ok(Some(published));
+            }
+        }
+        Ok(work
+            .locations
+            .into_iter()
+            .filter(|loc| loc.version.as_deref() == Some("publishedVersion"))
+            .filter_map(|loc| loc.landing_page_url)
+            .filter(|url| url.starts_with("https://doi.org/"))
+            .map(|url| strip(&url))
+            .find(is_other))
+    }
+}
+
+impl PdfSource for OpenAlex {
+    fn name(&self) -> &'static str {
+        "openalex"
+    }
+
+    fn pdf_url(&self, doi: &str, _title: Option<&str>) -> Result<Option<String>> {
+        let response = self.work::<Response>(doi)?;
+        let url = response
+            .best_oa_location
+            .and_then(|loc| loc.pdf_url)
+            .or_else(|| response.locations.into_iter().find_map(|loc| loc.pdf_url))
+            .or(response.open_access.oa_url);
+        Ok(url)
+    }
+}
+
+impl MetadataProvider for OpenAlex {
+    fn name(&self) -> &'static str {
+        "openalex"
+    }
+
+    fn metadata(&self, doi: &str) -> Result<EntryData> {
+        let work = self.work::<Work>(doi)?;
+        let mut fields = Map::new();
+        let crossref_type = work.type_crossref.as_deref().unwrap_or_default();
+        fields.insert("type".to_string(), crossref::csl_type(crossref_type).into());
+        let doi = work
+            .doi
+            .as_deref()
+            .map(|d| d.trim_start_matches("https://doi.org/"))
+            .unwrap_or(doi);
+        fields.insert("DOI".to_string(), doi.into());
+        if let Some(title) = work.title {
+            fields.insert("title".to_string(), title.into());
+        }
+        let authors: Vec<Value> = work
+            .authorships
+            .iter()
+            .filter_map(|a| a.author.display_name.as_deref().map(csl_name))
+            .collect();
+        fields.insert("author".to_string(), authors.into());
+        if let Some(issued) = work.publication_date.as_deref().and_then(csl_date) {
+            fields.insert("issued".to_string(), issued);
+        }
+        if let Some(container) = work
+            .primary_location
+            .and_then(|loc| loc.source)
+            .and_then(|source| source.display_name)
+        {
+            fields.insert("container-title".to_string(), container.into());
+        }
+        if let Some(biblio) = work.biblio {
+            for (field, value) in [("volume", biblio.volume), ("issue", biblio.issue)] {
+                if let Some(v) = value {
+                    fields.insert(field.to_string(), v.into());
+                }
+            }
+            if let Some(first) = biblio.first_page {
+                let page = match biblio.last_page {
+                    Some(last) if last != first => format!("{first}-{last}"),
+                    _ => first,
+                };
+                fields.insert("page".to_string(), page.into());
+            }
+        }
+        if let Some(language) = work.language {
+            fields.insert("language".to_string(), language.into());
+        }
+        if let Some(index) = work.abstract_inverted_index {
+            fields.insert("abstract".to_string(), reconstruct_abstract(index).into());
+        }
+
+        Ok(EntryData {
+            fields,
+            ..Default::default()
         })
-        .or(response.open_access.oa_url);
-    Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn openalex() -> OpenAlex {
+        OpenAlex {
+            email: "test@example.com".to_string(),
+            api_key: None,
+        }
+    }
+
+    #[test]
+    fn pdf_url_from_locations() {
+        assert_eq!(
+            openalex()
+                .pdf_url("10.7554/eLife.00001", None)
+                .unwrap()
+                .as_deref(),
+            Some("https://elifesciences.org/articles/00001.pdf")
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let data = openalex().metadata("10.7554/eLife.00001").unwrap();
+        data.validate().unwrap();
+        let fields = &data.fields;
+        assert_eq!(fields["type"], "article-journal");
+        assert_eq!(fields["DOI"], "10.7554/elife.00001");
+        assert_eq!(fields["container-title"], "eLife");
+        assert_eq!(fields["page"], "e00001");
+        assert_eq!(fields["abstract"], "Cells divide and cells die.");
+        assert_eq!(fields["author"][0]["family"], "Public");
+        assert_eq!(fields["author"][0]["given"], "Jane Q.");
+        assert_eq!(fields["issued"]["date-parts"][0][1], 1);
+    }
 }
@@ -2,12 +2,15 @@ use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::http;
+
 pub static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)10.\d{4,9}/osf.io/(.+)").unwrap());
 
 pub fn published_doi(osf_id: &str) -> Result<Option<String>> {
     Ok(
-        ureq::get(&format!("https://api.osf.io/v2/preprints/{osf_id}"))
+        http::get(&format!("https://api.osf.io/v2/preprints/{osf_id}"))
             .set("Accept", "application/vnd.api+json; charset=utf-8")
+            .cached()
             .call()?
             .into_json::<serde_json::Value>()?
             .get("data")
@@ -19,13 +22,35 @@ pub fn published_doi(osf_id: &str) -> Result<Option<String>> {
 }
 
 pub fn pdf_url(osf_id: &str) -> Result<String> {
-    Ok(ureq::get(&format!(
+    Ok(http::get(&format!(
         "https://api.osf.io/v2/preprints/{osf_id}/files/osfstorage/"
     ))
     .set("Accept", "application/vnd.api+json; charset=utf-8")
+    .cached()
     .call()?
     .into_json::<serde_json::Value>()?["data"][0]["links"]["download"]
         .as_str()
         .context("Could not find download link")?
         .to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_doi_from_attributes() {
+        assert_eq!(
+            published_doi("abc12").unwrap().as_deref(),
+            Some("10.1177/0956797619000000")
+        );
+    }
+
+    #[test]
+    fn pdf_url_from_files() {
+        assert_eq!(
+            pdf_url("abc12").unwrap(),
+            "https://osf.io/download/5cc9a1b2c3d4e5f6a7b8c9d0/"
+        );
+    }
+}
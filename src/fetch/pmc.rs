@@ -1,6 +1,9 @@
 use anyhow::Result;
 use serde::Deserialize;
 
+use super::PdfSource;
+use crate::http;
+
 #[derive(Deserialize)]
 struct Response {
     records: Vec<Record>,
@@ -11,11 +14,12 @@ struct Record {
     pmcid: Option<String>,
 }
 
-pub fn pdf_url(doi: &str) -> Result<Option<String>> {
+fn pdf_url(doi: &str) -> Result<Option<String>> {
     let response =
-        ureq::get("https://pmc.ncbi.nlm.nih.gov/tools/idconv/api/v1/articles/")
+        http::get("https://pmc.ncbi.nlm.nih.gov/tools/idconv/api/v1/articles/")
             .query("ids", doi)
             .query("format", "json")
+            .cached()
             .call()?
             .into_json::<Response>()?;
     let pmcid = response
@@ -24,3 +28,33 @@ pub fn pdf_url(doi: &str) -> Result<Option<String>> {
         .find_map(|r| r.pmcid);
     Ok(pmcid.map(|id| format!("https://pmc.ncbi.nlm.nih.gov/articles/{id}/pdf/")))
 }
+
+pub struct Pmc;
+
+impl PdfSource for Pmc {
+    fn name(&self) -> &'static str {
+        "pmc"
+    }
+
+    fn pdf_url(&self, doi: &str, _title: Option<&str>) -> Result<Option<String>> {
+        pdf_url(doi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdf_url_from_pmcid() {
+        assert_eq!(
+            pdf_url("10.7554/eLife.00001").unwrap().as_deref(),
+            Some("https://pmc.ncbi.nlm.nih.gov/articles/PMC3000001/pdf/")
+        );
+    }
+
+    #[test]
+    fn no_pmcid() {
+        assert_eq!(pdf_url("10.1016/j.cell.2020.01.001").unwrap(), None);
+    }
+}
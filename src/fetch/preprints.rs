@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+pub static RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(10.20944/preprints\d{6}\.\d{4}\.v\d+)").unwrap());
+pub static URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)preprints\.org/manuscript/(\d{6}\.\d{4})/v(\d+)").unwrap());
+static ID_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)preprints(\d{6}\.\d{4})\.v(\d+)").unwrap());
+
+pub fn doi_from_url(url: &str) -> Option<String> {
+    let caps = URL_RE.captures(url)?;
+    Some(format!("10.20944/preprints{}.v{}", &caps[1], &caps[2]))
+}
+
+pub fn pdf_url(doi: &str) -> Result<String> {
+    let caps = ID_RE.captures(doi).context("Invalid Preprints.org DOI")?;
+    Ok(format!(
+        "https://www.preprints.org/manuscript/{}/v{}/download",
+        &caps[1], &caps[2]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            doi_from_url("https://www.preprints.org/manuscript/202101.0001/v2").as_deref(),
+            Some("10.20944/preprints202101.0001.v2")
+        );
+        assert_eq!(
+            pdf_url("10.20944/preprints202101.0001.v2").unwrap(),
+            "https://www.preprints.org/manuscript/202101.0001/v2/download"
+        );
+    }
+}
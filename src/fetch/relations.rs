@@ -0,0 +1,110 @@
+//! Finds the published version of a preprint from the relations between works that
+//! CrossRef, DataCite and OpenAlex record. This works for any DOI, including preprints from
+//! servers that have no module of their own.
+
+use anyhow::Result;
+
+use super::{crossref::Crossref, datacite, openalex::OpenAlex};
+use crate::config::Config;
+
+/// DataCite relations that point from a preprint to its published version
+const DATACITE_RELATIONS: [&str; 2] = ["IsIdenticalTo", "IsPreviousVersionOf"];
+
+pub fn published_doi(doi: &str, conf: &Config) -> Result<Option<String>> {
+    let crossref = Crossref {
+        email: conf.email.clone(),
+    };
+    let openalex = OpenAlex {
+        email: conf.email.clone(),
+        api_key: conf.api_keys.openalex.clone(),
+    };
+    let mut failures = Vec::new();
+
+    match crossref.work(doi) {
+        Ok(work) => {
+            if let Some(published) = super::crossref::is_preprint_of(&work) {
+                return Ok(Some(published));
+            }
+            // Only preprints can have a published version
+            if work.get("type").and_then(|t| t.as_str()) != Some("posted-content") {
+                return Ok(None);
+            }
+            // The published version may link to the preprint even if the preprint does not
+            match crossref.works_with_preprint(doi) {
+                Ok(works) if !works.is_empty() => return Ok(works.into_iter().next()),
+                Ok(_) => {}
+                Err(e) => failures.push(format!("crossref: {e:#}")),
+            }
+        }
+        // The DOI may have been registered with DataCite instead
+        Err(e) => {
+            failures.push(format!("crossref: {e:#}"));
+            // A published version would have been registered by someone else
+            let prefix = doi.split('/').next().unwrap_or(doi);
+            match datacite::related_doi(doi, &DATACITE_RELATIONS, &format!("{prefix}/")) {
+                Ok(Some(published)) => return Ok(Some(published)),
+                Ok(None) => {}
+                Err(e) => failures.push(format!("datacite: {e:#}")),
+            }
+        }
+    }
+
+    match openalex.published_doi(doi) {
+        Ok(published) => Ok(published),
+        Err(e) if failures.is_empty() => Err(e),
+        Err(e) => {
+            failures.push(format!("openalex: {e:#}"));
+            anyhow::bail!(
+                "Could not look up the relations of {doi}:\n  {}",
+                failures.join("\n  ")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conf() -> Config {
+        Config {
+            email: "test@example.com".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn published_version_links_to_preprint() {
+        assert_eq!(
+            published_doi("10.9999/preprint.1", &conf())
+                .unwrap()
+                .as_deref(),
+            Some("10.9999/journal.1")
+        );
+    }
+
+    #[test]
+    fn openalex_merged_versions() {
+        assert_eq!(
+            published_doi("10.9999/preprint.2", &conf())
+                .unwrap()
+                .as_deref(),
+            Some("10.9999/journal.2")
+        );
+    }
+
+    #[test]
+    fn not_a_preprint() {
+        assert_eq!(published_doi("10.9999/journal.3", &conf()).unwrap(), None);
+    }
+
+    #[test]
+    fn preprint_of_relation() {
+        assert_eq!(
+            published_doi("10.21203/rs.3.rs-123456/v1", &conf())
+                .unwrap()
+                .as_deref(),
+            Some("10.1038/s41467-021-000001")
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::regex::cap_as_str;
+
+pub static RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(10.21203/rs\.3\.rs-\d+/v\d+)").unwrap());
+pub static URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)researchsquare\.com/article/(rs-\d+/v\d+)").unwrap());
+static ID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)(rs-\d+/v\d+)").unwrap());
+
+pub fn doi_from_url(url: &str) -> Option<String> {
+    cap_as_str(&URL_RE, url, 1).map(|id| format!("10.21203/rs.3.{id}"))
+}
+
+pub fn pdf_url(doi: &str) -> Result<String> {
+    let id = cap_as_str(&ID_RE, doi, 1).context("Invalid Research Square DOI")?;
+    Ok(format!("https://www.researchsquare.com/article/{id}.pdf"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::crossref::Crossref;
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            doi_from_url("https://www.researchsquare.com/article/rs-123456/v1").as_deref(),
+            Some("10.21203/rs.3.rs-123456/v1")
+        );
+        assert_eq!(
+            pdf_url("10.21203/rs.3.rs-123456/v1").unwrap(),
+            "https://www.researchsquare.com/article/rs-123456/v1.pdf"
+        );
+    }
+
+    #[test]
+    fn published_doi_from_crossref_relation() {
+        let crossref = Crossref {
+            email: "test@example.com".to_string(),
+        };
+        assert_eq!(
+            crossref
+                .preprint_of("10.21203/rs.3.rs-123456/v1")
+                .unwrap()
+                .as_deref(),
+            Some("10.1038/s41467-021-000001")
+        );
+    }
+}
@@ -1,5 +1,11 @@
 use anyhow::Result;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::{csl_date, csl_name, MetadataProvider, PdfSource};
+use crate::entry::EntryData;
+use crate::http;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -13,23 +19,173 @@ struct OpenAccessPdf {
     url: String,
 }
 
-pub fn pdf_url(doi: &str, expected_title: Option<&str>) -> Result<Option<String>> {
-    let response = ureq::get(&format!(
-        "https://api.semanticscholar.org/graph/v1/paper/DOI:{doi}"
-    ))
-    .query("fields", "title,openAccessPdf")
-    .call()?
-    .into_json::<Response>()?;
-    if let (Some(expected), Some(actual)) = (expected_title, response.title.as_deref()) {
-        if expected.trim().to_lowercase() != actual.trim().to_lowercase() {
-            eprintln!(
-                "WARNING: Semantic Scholar returned a different paper title for this DOI, skipping."
-            );
-            return Ok(None);
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Paper {
+    title: Option<String>,
+    #[serde(default)]
+    authors: Vec<Author>,
+    year: Option<u64>,
+    publication_date: Option<String>,
+    venue: Option<String>,
+    journal: Option<Journal>,
+    #[serde(rename = "abstract")]
+    abstract_: Option<String>,
+    publication_types: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Author {
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct Journal {
+    name: Option<String>,
+    volume: Option<String>,
+    pages: Option<String>,
+}
+
+/// Maps Semantic Scholar's publication types onto CSL item types
+fn csl_type(publication_types: &[String]) -> &'static str {
+    for t in publication_types {
+        match t.as_str() {
+            "JournalArticle" => return "article-journal",
+            "Conference" => return "paper-conference",
+            "Book" => return "book",
+            "BookSection" => return "chapter",
+            "Dataset" => return "dataset",
+            "Review" => return "review",
+            _ => {}
         }
     }
-    Ok(response
-        .open_access_pdf
-        .map(|pdf| pdf.url)
-        .filter(|url| !url.is_empty()))
+    "article"
+}
+
+pub struct SemanticScholar {
+    pub api_key: Option<String>,
+}
+
+impl SemanticScholar {
+    fn paper<T: DeserializeOwned>(&self, doi: &str, fields: &str) -> Result<T> {
+        let mut request = http::get(&format!(
+            "https://api.semanticscholar.org/graph/v1/paper/DOI:{doi}"
+        ))
+        .query("fields", fields);
+        if let Some(key) = &self.api_key {
+            request = request.set("x-api-key", key);
+        }
+        Ok(request.cached().call()?.into_json::<T>()?)
+    }
+}
+
+impl PdfSource for SemanticScholar {
+    fn name(&self) -> &'static str {
+        "semantic_scholar"
+    }
+
+    fn pdf_url(&self, doi: &str, expected_title: Option<&str>) -> Result<Option<String>> {
+        let response = self.paper::<Response>(doi, "title,openAccessPdf")?;
+        if let (Some(expected), Some(actual)) = (expected_title, response.title.as_deref()) {
+            if expected.trim().to_lowercase() != actual.trim().to_lowercase() {
+                anyhow::bail!("returned a different paper title for this DOI: {actual:?}");
+            }
+        }
+        Ok(response
+            .open_access_pdf
+            .map(|pdf| pdf.url)
+            .filter(|url| !url.is_empty()))
+    }
+}
+
+impl MetadataProvider for SemanticScholar {
+    fn name(&self) -> &'static str {
+        "semantic_scholar"
+    }
+
+    fn metadata(&self, doi: &str) -> Result<EntryData> {
+        let paper = self.paper::<Paper>(
+            doi,
+            "title,authors,year,publicationDate,venue,journal,abstract,publicationTypes",
+        )?;
+        let mut fields = Map::new();
+        let publication_types = paper.publication_types.unwrap_or_default();
+        fields.insert("type".to_string(), csl_type(&publication_types).into());
+        fields.insert("DOI".to_string(), doi.into());
+        if let Some(title) = paper.title {
+            fields.insert("title".to_string(), title.into());
+        }
+        let authors: Vec<Value> = paper
+            .authors
+            .iter()
+            .filter_map(|a| a.name.as_deref().map(csl_name))
+            .collect();
+        fields.insert("author".to_string(), authors.into());
+        let issued = paper
+            .publication_date
+            .as_deref()
+            .and_then(csl_date)
+            .or_else(|| paper.year.and_then(|y| csl_date(&y.to_string())));
+        if let Some(issued) = issued {
+            fields.insert("issued".to_string(), issued);
+        }
+        let journal = paper.journal.unwrap_or_default();
+        let container = journal.name.or(paper.venue).filter(|v| !v.is_empty());
+        for (field, value) in [
+            ("container-title", container),
+            ("volume", journal.volume),
+            ("page", journal.pages.map(|p| p.trim().to_string())),
+            ("abstract", paper.abstract_),
+        ] {
+            if let Some(v) = value {
+                fields.insert(field.to_string(), v.into());
+            }
+        }
+
+        Ok(EntryData {
+            fields,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOI: &str = "10.7554/eLife.00001";
+
+    fn semantic_scholar() -> SemanticScholar {
+        SemanticScholar { api_key: None }
+    }
+
+    #[test]
+    fn pdf_url_when_title_matches() {
+        assert_eq!(
+            semantic_scholar()
+                .pdf_url(DOI, Some("An example eLife article"))
+                .unwrap()
+                .as_deref(),
+            Some("https://elifesciences.org/articles/00001.pdf")
+        );
+    }
+
+    #[test]
+    fn pdf_url_rejects_other_paper() {
+        assert!(semantic_scholar()
+            .pdf_url(DOI, Some("A different article"))
+            .is_err());
+    }
+
+    #[test]
+    fn metadata() {
+        let data = semantic_scholar().metadata(DOI).unwrap();
+        data.validate().unwrap();
+        let fields = &data.fields;
+        assert_eq!(fields["type"], "article-journal");
+        assert_eq!(fields["title"], "An Example eLife Article");
+        assert_eq!(fields["container-title"], "eLife");
+        assert_eq!(fields["author"][1]["family"], "Smith");
+        assert_eq!(fields["issued"]["date-parts"][0][0], 2013);
+    }
 }
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::regex::cap_as_str;
+
+pub static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)(10.2139/ssrn\.\d+)").unwrap());
+pub static URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)ssrn\.com/.*abstract(?:_?id)?=(\d+)").unwrap());
+static ID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)ssrn\.(\d+)").unwrap());
+
+pub fn doi_from_url(url: &str) -> Option<String> {
+    cap_as_str(&URL_RE, url, 1).map(|id| format!("10.2139/ssrn.{id}"))
+}
+
+pub fn pdf_url(doi: &str) -> Result<String> {
+    let id = cap_as_str(&ID_RE, doi, 1).context("Invalid SSRN DOI")?;
+    Ok(format!(
+        "https://papers.ssrn.com/sol3/Delivery.cfm?abstractid={id}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            doi_from_url("https://papers.ssrn.com/sol3/papers.cfm?abstract_id=3456789").as_deref(),
+            Some("10.2139/ssrn.3456789")
+        );
+        assert_eq!(
+            pdf_url("10.2139/ssrn.3456789").unwrap(),
+            "https://papers.ssrn.com/sol3/Delivery.cfm?abstractid=3456789"
+        );
+    }
+}
@@ -3,6 +3,9 @@ use std::cmp::Ordering;
 use anyhow::Result;
 use serde::Deserialize;
 
+use super::PdfSource;
+use crate::http;
+
 /// A response from the Unpaywall API
 /// See https://unpaywall.org/data-format
 #[allow(dead_code)]
@@ -53,10 +56,11 @@ impl PartialOrd for Version {
     }
 }
 
-pub fn pdf_url(doi: &str, email: &str) -> Result<Option<String>> {
-    let response = ureq::get(&format!("https://api.unpaywall.org/v2/{doi}"))
+fn pdf_url(doi: &str, email: &str) -> Result<Option<String>> {
+    let response = http::get(&format!("https://api.unpaywall.org/v2/{doi}"))
         .set("Accept", "application/json; charset=utf-8")
         .query("email", email)
+        .cached()
         .call()?
         .into_json::<Response>()?;
     let result = response
@@ -78,3 +82,50 @@ pub fn pdf_url(doi: &str, email: &str) -> Result<Option<String>> {
     }
     Ok(result.and_then(|loc| loc.url_for_pdf))
 }
+
+pub struct Unpaywall {
+    pub email: String,
+}
+
+impl PdfSource for Unpaywall {
+    fn name(&self) -> &'static str {
+        "unpaywall"
+    }
+
+    fn pdf_url(&self, doi: &str, _title: Option<&str>) -> Result<Option<String>> {
+        pdf_url(doi, &self.email)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unpaywall() -> Unpaywall {
+        Unpaywall {
+            email: "test@example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn prefers_published_version() {
+        assert_eq!(
+            unpaywall()
+                .pdf_url("10.7554/eLife.00001", None)
+                .unwrap()
+                .as_deref(),
+            Some("https://elifesciences.org/articles/00001.pdf")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_accepted_version() {
+        assert_eq!(
+            unpaywall()
+                .pdf_url("10.1016/j.cell.2020.01.001", None)
+                .unwrap()
+                .as_deref(),
+            Some("https://www.ncbi.nlm.nih.gov/pmc/articles/PMC7000001/pdf/nihms-1000001.pdf")
+        );
+    }
+}
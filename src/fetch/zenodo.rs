@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+
+use super::datacite;
+use crate::http;
+use crate::regex::cap_as_str;
+
+pub static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)(10.5281/zenodo\.\d+)").unwrap());
+pub static URL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)zenodo\.org/(?:records?|deposit)/(\d+)").unwrap());
+static ID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)zenodo\.(\d+)").unwrap());
+
+#[derive(Deserialize)]
+struct Record {
+    #[serde(default)]
+    files: Vec<File>,
+}
+
+#[derive(Deserialize)]
+struct File {
+    key: String,
+    links: FileLinks,
+}
+
+#[derive(Deserialize)]
+struct FileLinks {
+    #[serde(rename = "self")]
+    self_: String,
+}
+
+pub fn doi_from_url(url: &str) -> Option<String> {
+    cap_as_str(&URL_RE, url, 1).map(|id| format!("10.5281/zenodo.{id}"))
+}
+
+/// Zenodo records link to the published version of a preprint as an identical (or newer) work
+pub fn published_doi(doi: &str) -> Result<Option<String>> {
+    datacite::related_doi(doi, &["IsIdenticalTo", "IsPreviousVersionOf"], "10.5281/")
+}
+
+pub fn pdf_url(doi: &str) -> Result<String> {
+    let id = cap_as_str(&ID_RE, doi, 1).context("Invalid Zenodo DOI")?;
+    http::get(&format!("https://zenodo.org/api/records/{id}"))
+        .set("Accept", "application/json")
+        .cached()
+        .call()?
+        .into_json::<Record>()?
+        .files
+        .into_iter()
+        .find(|f| f.key.to_lowercase().ends_with(".pdf"))
+        .map(|f| f.links.self_)
+        .context("The Zenodo record has no PDF files")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOI: &str = "10.5281/zenodo.1234567";
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            doi_from_url("https://zenodo.org/records/1234567").as_deref(),
+            Some(DOI)
+        );
+    }
+
+    #[test]
+    fn published_doi_from_related_identifiers() {
+        assert_eq!(
+            published_doi(DOI).unwrap().as_deref(),
+            Some("10.1093/example/abc001")
+        );
+    }
+
+    #[test]
+    fn pdf_url_from_files() {
+        assert_eq!(
+            pdf_url(DOI).unwrap(),
+            "https://zenodo.org/api/records/1234567/files/Preprint.PDF/content"
+        );
+    }
+}
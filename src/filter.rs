@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use ::regex::{Regex, RegexBuilder};
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use clap::Args;
+use itertools::Itertools;
+
+use crate::config::Config;
+use crate::entry::{Entry, ReadingStatus};
+use crate::query::Query;
+use crate::{notes, ops, tags};
+
+/// Looks up a collection (e.g. "@thesis") in the config
+pub fn collection_query(name: &str, conf: &Config) -> Result<Query> {
+    let Some(collection_name) = name.strip_prefix('@') else {
+        anyhow::bail!("Expected the name of a collection starting with @, e.g. @thesis: {name}");
+    };
+    let collection = conf.collections.get(collection_name).with_context(|| {
+        format!("Unknown collection {name} (collections are defined in the config)")
+    })?;
+    Query::parse(&collection.query).with_context(|| format!("Invalid query for {name}"))
+}
+
+/// Parses a command line argument that must name a collection (e.g. "@thesis")
+pub fn parse_collection(name: &str) -> Result<String> {
+    if !name.starts_with('@') {
+        anyhow::bail!("Expected the name of a collection starting with @, e.g. @thesis");
+    }
+    Ok(name.to_string())
+}
+
+/// Replaces each collection (e.g. "@thesis") in a list of keys with the keys of its entries
+pub fn expand_keys(keys: &[String], root: &Path, conf: &Config) -> Result<Vec<String>> {
+    let mut expanded = Vec::new();
+    for key in keys {
+        if !key.starts_with('@') {
+            expanded.push(key.clone());
+            continue;
+        }
+        let query = collection_query(key, conf)?;
+        for path in ops::all_entry_paths(root)? {
+            let key = ops::key_from_path(&path);
+            if query.matches(root, key, &ops::read_entry(&ops::data_path(root, key))?)? {
+                expanded.push(key.to_string());
+            }
+        }
+    }
+    Ok(expanded.into_iter().unique().collect())
+}
+
+/// Options for selecting entries, shared by the commands that operate on part of the library
+#[derive(Args, Debug)]
+pub struct Filters {
+    /// Only include entries in any of these collections (as defined in the config)
+    #[arg(value_name = "@COLLECTION", value_parser = parse_collection)]
+    pub collections: Vec<String>,
+
+    /// Only include entries that match a query, e.g. "tag:thesis -tag:excluded year:2015.."
+    #[arg(long)]
+    pub query: Option<String>,
+
+    /// Only include entries that have each of these tags, or a tag beneath it
+    /// (e.g. `project` matches `project/grant-2025`)
+    #[arg(long, num_args(1..))]
+    pub tag: Vec<String>,
+
+    /// Only include entries that have any of these tags
+    #[arg(long, num_args(1..), conflicts_with = "all_tags")]
+    pub any_tag: Option<Vec<String>>,
+
+    /// Only include entries that have all of these tags
+    #[arg(long, num_args(1..), conflicts_with = "any_tag")]
+    pub all_tags: Option<Vec<String>>,
+
+    /// Only include entries that have notes
+    #[arg(long, default_value_t = false)]
+    pub has_notes: bool,
+
+    /// Only include entries whose notes match a (case-insensitive) regular expression
+    #[arg(long, value_name = "PATTERN")]
+    pub notes_match: Option<String>,
+
+    /// Only include entries with any of these reading statuses
+    #[arg(long, value_enum, num_args(1..))]
+    pub status: Option<Vec<ReadingStatus>>,
+
+    /// Only include entries rated at least this highly (1 to 5)
+    #[arg(long, value_name = "RATING", value_parser = clap::value_parser!(u8).range(1..=5))]
+    pub min_rating: Option<u8>,
+
+    /// Only include entries added on or after a date (e.g. 2026-09-01)
+    #[arg(long, value_name = "DATE")]
+    pub since: Option<NaiveDate>,
+}
+
+impl Filters {
+    fn notes_re(&self) -> Result<Option<Regex>> {
+        Ok(self
+            .notes_match
+            .as_ref()
+            .map(|p| RegexBuilder::new(p).case_insensitive(true).build())
+            .transpose()?)
+    }
+
+    fn matches_tags(&self, entry: &Entry) -> bool {
+        let entry_tags = &entry.data.tags;
+        let within = |tag: &String| entry_tags.iter().any(|t| tags::is_within(t, tag));
+        if !self.tag.iter().all(within) {
+            return false;
+        }
+        match (&self.any_tag, &self.all_tags) {
+            (Some(tags), _) => tags.iter().any(|t| entry_tags.contains(t)),
+            (_, Some(tags)) => tags.iter().all(|t| entry_tags.contains(t)),
+            (None, None) => true,
+        }
+    }
+
+    /// Reads the entries in the library that match the filters
+    pub fn select(&self, root: &Path, conf: &Config) -> Result<Vec<(String, Entry)>> {
+        let notes_re = self.notes_re()?;
+        let collections = self
+            .collections
+            .iter()
+            .map(|c| collection_query(c, conf))
+            .collect::<Result<Vec<_>>>()?;
+        let query = self.query.as_deref().map(Query::parse).transpose()?;
+        let mut selected = Vec::new();
+        for path in ops::all_entry_paths(root)? {
+            let key = ops::key_from_path(&path).to_string();
+            let entry = ops::read_entry(&ops::data_path(root, &key))?;
+            if !self.matches_tags(&entry) {
+                continue;
+            }
+            // Like lists of keys, several collections select the entries in any of them
+            if !collections.is_empty()
+                && !collections
+                    .iter()
+                    .map(|c| c.matches(root, &key, &entry))
+                    .fold_ok(false, |any, matches| any || matches)?
+            {
+                continue;
+            }
+            if let Some(query) = &query {
+                if !query.matches(root, &key, &entry)? {
+                    continue;
+                }
+            }
+            if let Some(statuses) = &self.status {
+                if !entry.data.status.is_some_and(|s| statuses.contains(&s)) {
+                    continue;
+                }
+            }
+            if let Some(min_rating) = self.min_rating {
+                if !entry.data.rating.is_some_and(|r| r >= min_rating) {
+                    continue;
+                }
+            }
+            if let Some(since) = self.since {
+                let added = entry
+                    .data
+                    .added
+                    .map(|a| a.with_timezone(&Local).date_naive());
+                if !added.is_some_and(|a| a >= since) {
+                    continue;
+                }
+            }
+            if self.has_notes || notes_re.is_some() {
+                let notes = notes::read_notes(root, &key)?;
+                let matches = match (&notes, &notes_re) {
+                    (Some(n), Some(re)) => re.is_match(n),
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                };
+                if !matches {
+                    continue;
+                }
+            }
+            selected.push((key, entry));
+        }
+        Ok(selected)
+    }
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// A successful response, as stored on disk
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CachedResponse {
+    pub url: String,
+    pub accept: Option<String>,
+    pub status: u16,
+    pub status_text: String,
+    pub content_type: String,
+    pub body: String,
+    /// Seconds since the unix epoch
+    pub fetched_at: u64,
+    /// Set on test fixtures that were written by hand rather than recorded from a real response
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub synthetic: bool,
+}
+
+impl CachedResponse {
+    pub fn from_response(
+        url: &str,
+        accept: Option<&str>,
+        response: ureq::Response,
+    ) -> Result<Self> {
+        Ok(Self {
+            url: url.to_string(),
+            accept: accept.map(|s| s.to_string()),
+            status: response.status(),
+            status_text: response.status_text().to_string(),
+            content_type: response.content_type().to_string(),
+            body: response.into_string()?,
+            fetched_at: now(),
+            synthetic: false,
+        })
+    }
+
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+
+    pub fn to_response(&self) -> Result<ureq::Response> {
+        Ok(format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\n\r\n{}",
+            self.status, self.status_text, self.content_type, self.body
+        )
+        .parse()?)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// 64-bit FNV-1a, used instead of `DefaultHasher` because its output must be stable
+/// across builds for cache entries to be found again.
+pub(super) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn cache_path(url: &str, accept: Option<&str>) -> PathBuf {
+    let key = format!("{url}\n{}", accept.unwrap_or_default());
+    config::cache_dir()
+        .join("http")
+        .join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+}
+
+pub fn load(url: &str, accept: Option<&str>) -> Option<CachedResponse> {
+    let content = std::fs::read_to_string(cache_path(url, accept)).ok()?;
+    serde_json::from_str::<CachedResponse>(&content)
+        .ok()
+        // Guard against hash collisions
+        .filter(|c| c.url == url && c.accept.as_deref() == accept)
+}
+
+pub fn store(cached: &CachedResponse) -> Result<()> {
+    let path = cache_path(&cached.url, cached.accept.as_deref());
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string(cached)?)?;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Recording and replaying of API responses, so that the fetchers can be run (and tested)
+//! without network access. Set `PICOREF_HTTP_RECORD=<dir>` to save every API response to `dir`,
+//! and `PICOREF_HTTP_REPLAY=<dir>` to serve requests only from the responses saved there.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::cache::{fnv1a, CachedResponse};
+use super::without_params;
+
+/// Query parameters that are left out of recorded URLs, since fixtures are meant to be committed
+/// and these hold credentials or the user's email address
+const PRIVATE_PARAMS: [&str; 3] = ["api_key", "mailto", "email"];
+
+pub enum Fixtures {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Fixtures {
+    pub fn from_env() -> Option<Self> {
+        let var = |name| std::env::var_os(name).filter(|v| !v.is_empty());
+        if let Some(dir) = var("PICOREF_HTTP_RECORD") {
+            return Some(Self::Record(dir.into()));
+        }
+        if let Some(dir) = var("PICOREF_HTTP_REPLAY") {
+            return Some(Self::Replay(dir.into()));
+        }
+        // Tests must never touch the network
+        if cfg!(test) {
+            return Some(Self::Replay(
+                Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/http"),
+            ));
+        }
+        None
+    }
+}
+
+/// Fixtures are named after the host so that they are easy to find when updating them.
+/// `url` must not contain any `PRIVATE_PARAMS`.
+fn fixture_path(dir: &Path, url: &str, accept: Option<&str>) -> PathBuf {
+    let host = url
+        .split_once("://")
+        .and_then(|(_, rest)| rest.split(['/', '?', ':']).next())
+        .unwrap_or_default();
+    let key = format!("{url}\n{}", accept.unwrap_or_default());
+    dir.join(format!("{host}-{:016x}.json", fnv1a(key.as_bytes())))
+}
+
+pub fn load(dir: &Path, url: &str, accept: Option<&str>) -> Result<ureq::Response> {
+    let url = without_params(url, &PRIVATE_PARAMS);
+    let path = fixture_path(dir, &url, accept);
+    let content = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "No recorded response for {url} (expected at {})",
+            path.to_string_lossy()
+        )
+    })?;
+    let fixture: CachedResponse = serde_json::from_str(&content)
+        .with_context(|| format!("Could not parse {}", path.to_string_lossy()))?;
+    if fixture.url != url || fixture.accept.as_deref() != accept {
+        anyhow::bail!(
+            "{} was recorded for a different request",
+            path.to_string_lossy()
+        );
+    }
+    fixture.to_response()
+}
+
+pub fn store(dir: &Path, response: &CachedResponse) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let response = CachedResponse {
+        url: without_params(&response.url, &PRIVATE_PARAMS),
+        ..response.clone()
+    };
+    let path = fixture_path(dir, &response.url, response.accept.as_deref());
+    // Pretty-printed so that changes to fixtures are easy to review
+    std::fs::write(path, serde_json::to_string_pretty(&response)? + "\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_replays() {
+        let dir = std::env::temp_dir().join(format!("picoref-fixtures-{}", std::process::id()));
+        let url = "https://api.openalex.org/works/doi:10.1/x?mailto=a%40b.c&api_key=secret";
+        let accept = Some("application/json");
+        let response: ureq::Response =
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"id\": 1}"
+                .parse()
+                .unwrap();
+        store(
+            &dir,
+            &CachedResponse::from_response(url, accept, response).unwrap(),
+        )
+        .unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let recorded = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(!recorded.contains("secret") && !recorded.contains("a%40b.c"));
+        assert!(!recorded.contains("synthetic"));
+
+        let replayed = load(&dir, url, accept).unwrap();
+        assert_eq!(replayed.status(), 200);
+        assert_eq!(replayed.content_type(), "application/json");
+        assert_eq!(replayed.into_string().unwrap(), "{\"id\": 1}");
+        assert!(load(&dir, url, Some("text/html")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
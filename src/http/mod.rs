@@ -0,0 +1,323 @@
+//! A thin wrapper around `ureq` shared by everything that talks to the network.
+//! It applies per-host rate limits, retries transient failures with exponential backoff,
+//! and caches API responses on disk (or records and replays them, see `fixtures`).
+
+mod cache;
+mod fixtures;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use once_cell::sync::{Lazy, OnceCell};
+use ureq::ErrorKind;
+
+use self::cache::CachedResponse;
+use self::fixtures::Fixtures;
+use crate::config::{Config, NetworkConfig};
+
+/// Status codes that indicate a failure that may go away if the request is repeated
+const RETRY_STATUSES: [u16; 6] = [408, 429, 500, 502, 503, 504];
+
+/// Removes query parameters from a URL
+fn without_params(url: &str, params: &[&str]) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| !params.iter().any(|p| pair.split('=').next() == Some(*p)))
+        .collect();
+    if kept.is_empty() {
+        base.to_string()
+    } else {
+        format!("{base}?{}", kept.join("&"))
+    }
+}
+
+struct Settings {
+    agent: ureq::Agent,
+    network: NetworkConfig,
+    /// Only serve responses from the cache
+    offline: bool,
+    fixtures: Option<Fixtures>,
+}
+
+static SETTINGS: OnceCell<Settings> = OnceCell::new();
+/// The earliest time at which the next request to each host may be sent
+static NEXT_REQUEST: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(Default::default);
+
+/// Sets the configuration used for all requests (must be called before any requests are made)
+pub fn init(conf: &Config, offline: bool) -> Result<()> {
+    let network = &conf.network;
+    let user_agent = network.user_agent.clone().unwrap_or_else(|| {
+        format!(
+            "picoref/{} (mailto:{})",
+            env!("CARGO_PKG_VERSION"),
+            conf.email
+        )
+    });
+    let mut builder = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(network.connect_timeout_secs))
+        .timeout_read(Duration::from_secs(network.read_timeout_secs))
+        .user_agent(&user_agent);
+    let proxy = network.proxy.clone().or_else(|| {
+        ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|p| !p.is_empty()))
+    });
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(ureq::Proxy::new(&proxy).context("Invalid proxy")?);
+    }
+    let _ = SETTINGS.set(Settings {
+        agent: builder.build(),
+        network: network.clone(),
+        offline,
+        fixtures: Fixtures::from_env(),
+    });
+    Ok(())
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| Settings {
+        agent: ureq::Agent::new(),
+        network: NetworkConfig::default(),
+        offline: false,
+        fixtures: Fixtures::from_env(),
+    })
+}
+
+pub fn get(url: &str) -> Request {
+    Request {
+        inner: settings().agent.get(url),
+        cached: false,
+        secrets: Vec::new(),
+    }
+}
+
+pub struct Request {
+    inner: ureq::Request,
+    cached: bool,
+    /// Query parameters that hold credentials, as (name, value)
+    secrets: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn set(self, header: &str, value: &str) -> Self {
+        Self {
+            inner: self.inner.set(header, value),
+            ..self
+        }
+    }
+
+    pub fn query(self, param: &str, value: &str) -> Self {
+        Self {
+            inner: self.inner.query(param, value),
+            ..self
+        }
+    }
+
+    /// Adds a query parameter holding a credential (e.g. an API key), which is left out of the
+    /// URL that responses are cached under and that is shown in errors
+    pub fn secret_query(mut self, param: &str, value: &str) -> Self {
+        self.secrets.push((param.to_string(), value.to_string()));
+        self.query(param, value)
+    }
+
+    /// The URL of the request, without any credentials
+    fn public_url(&self) -> Result<String> {
+        let url = self.inner.request_url()?.as_url().to_string();
+        let secrets: Vec<&str> = self.secrets.iter().map(|(p, _)| p.as_str()).collect();
+        Ok(without_params(&url, &secrets))
+    }
+
+    /// Converts an error, hiding any credentials in the URL it mentions
+    fn redact(&self, err: ureq::Error) -> anyhow::Error {
+        if self.secrets.is_empty() {
+            return err.into();
+        }
+        let message = self.secrets.iter().fold(err.to_string(), |m, (_, value)| {
+            m.replace(value, "REDACTED")
+        });
+        anyhow::anyhow!(message)
+    }
+
+    /// Serve this request from (and store its response in) the on-disk cache.
+    /// Only use this for small, textual responses (i.e. API calls, not file downloads).
+    pub fn cached(self) -> Self {
+        Self {
+            cached: true,
+            ..self
+        }
+    }
+
+    pub fn call(self) -> Result<ureq::Response> {
+        let Settings {
+            network,
+            offline,
+            fixtures,
+            ..
+        } = settings();
+        let url = self.public_url()?;
+        let accept = self.inner.header("Accept");
+        let record_to = match fixtures {
+            Some(Fixtures::Replay(dir)) => return fixtures::load(dir, &url, accept),
+            Some(Fixtures::Record(dir)) if self.cached => Some(dir),
+            _ => None,
+        };
+        let ttl = Duration::from_secs(network.cache_ttl_hours * 60 * 60);
+        // Recording must capture real responses rather than cached ones
+        let use_cache = self.cached && record_to.is_none() && (*offline || !ttl.is_zero());
+
+        if use_cache {
+            if let Some(cached) = cache::load(&url, accept) {
+                // Stale responses are better than nothing when offline
+                if *offline || cached.age() < ttl {
+                    return cached.to_response();
+                }
+            }
+        }
+        if *offline {
+            if self.cached {
+                anyhow::bail!("No cached response for {url} (running in offline mode)");
+            }
+            anyhow::bail!("Cannot fetch {url} in offline mode");
+        }
+
+        let response = self.send(network)?;
+        if (use_cache || record_to.is_some()) && response.status() == 200 {
+            let cached = CachedResponse::from_response(&url, accept, response)?;
+            if let Some(dir) = record_to {
+                fixtures::store(dir, &cached)
+                    .with_context(|| format!("Could not record response from {url}"))?;
+            }
+            if use_cache {
+                if let Err(e) = cache::store(&cached) {
+                    eprintln!("WARNING: Could not cache response from {url}: {e}");
+                }
+            }
+            return cached.to_response();
+        }
+        Ok(response)
+    }
+
+    /// Sends the request, retrying transient failures until the retry budget is exhausted
+    fn send(&self, conf: &NetworkConfig) -> Result<ureq::Response> {
+        let host = self.inner.request_url()?.host().to_string();
+        let mut retries = 0;
+        loop {
+            wait_for_rate_limit(&host, conf);
+            let err = match self.inner.clone().call() {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            let retry_after = match &err {
+                ureq::Error::Status(status, response) if RETRY_STATUSES.contains(status) => {
+                    response
+                        .header("Retry-After")
+                        .and_then(|s| s.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs)
+                }
+                ureq::Error::Transport(t)
+                    if matches!(
+                        t.kind(),
+                        ErrorKind::Dns | ErrorKind::ConnectionFailed | ErrorKind::Io
+                    ) =>
+                {
+                    None
+                }
+                _ => return Err(self.redact(err)),
+            };
+            if retries >= conf.max_retries {
+                return Err(self
+                    .redact(err)
+                    .context(format!("Request failed after {retries} retries")));
+            }
+            let delay = retry_after.unwrap_or_else(|| backoff(conf.retry_base_delay_ms, retries));
+            if delay > Duration::from_secs(conf.max_retry_delay_secs) {
+                return Err(self.redact(err).context(format!(
+                    "{host} asked to retry after {}s, which is longer than allowed",
+                    delay.as_secs()
+                )));
+            }
+            eprintln!(
+                "Request to {host} failed ({}), retrying in {:.1}s",
+                self.redact(err),
+                delay.as_secs_f32()
+            );
+            sleep(delay);
+            retries += 1;
+        }
+    }
+}
+
+/// The delay before a retry, which doubles with each retry (saturating rather than overflowing)
+fn backoff(base_delay_ms: u64, retries: u32) -> Duration {
+    let factor = 1u64.checked_shl(retries).unwrap_or(u64::MAX);
+    Duration::from_millis(base_delay_ms.saturating_mul(factor))
+}
+
+/// Blocks until a request to `host` is allowed by its rate limit
+fn wait_for_rate_limit(host: &str, conf: &NetworkConfig) {
+    let per_second = conf
+        .rate_limits
+        .get(host)
+        .copied()
+        .unwrap_or(conf.default_rate_limit);
+    if per_second <= 0.0 {
+        return;
+    }
+    let interval = Duration::from_secs_f64(1.0 / per_second);
+    let wait = {
+        let mut next_request = NEXT_REQUEST.lock().expect("lock to not be poisoned");
+        let now = Instant::now();
+        let scheduled = next_request.get(host).copied().unwrap_or(now).max(now);
+        next_request.insert(host.to_string(), scheduled + interval);
+        scheduled - now
+    };
+    if !wait.is_zero() {
+        sleep(wait);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_params() {
+        let url = "https://api.openalex.org/works/doi:10.1/x?mailto=a%40b.c&api_key=secret";
+        assert_eq!(
+            without_params(url, &["api_key"]),
+            "https://api.openalex.org/works/doi:10.1/x?mailto=a%40b.c"
+        );
+        assert_eq!(
+            without_params(url, &["api_key", "mailto"]),
+            "https://api.openalex.org/works/doi:10.1/x"
+        );
+        assert_eq!(
+            without_params("https://a.b/c", &["api_key"]),
+            "https://a.b/c"
+        );
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        assert_eq!(backoff(500, 0), Duration::from_millis(500));
+        assert_eq!(backoff(500, 3), Duration::from_millis(4000));
+        assert_eq!(backoff(0, 64), Duration::ZERO);
+        assert_eq!(backoff(500, 100), Duration::from_millis(u64::MAX));
+    }
+
+    #[test]
+    fn hides_secrets() {
+        let request = get("https://api.openalex.org/works/doi:10.1/x")
+            .query("mailto", "a@b.c")
+            .secret_query("api_key", "secret");
+        let url = request.public_url().unwrap();
+        assert!(!url.contains("secret"));
+        assert!(url.contains("mailto"));
+    }
+}
@@ -0,0 +1,295 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+use crate::entry::Entry;
+use crate::names::{display_list, parse_names};
+
+static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{([a-z]+)\}").unwrap());
+
+/// The fields that can be used in a `--template`
+const FIELDS: [&str; 12] = [
+    "key",
+    "year",
+    "author",
+    "title",
+    "type",
+    "container",
+    "doi",
+    "tags",
+    "added",
+    "modified",
+    "status",
+    "rating",
+];
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One key per line
+    Keys,
+    /// Aligned columns with the key, year, authors, and title
+    Table,
+    /// A JSON array of the entries' metadata
+    Json,
+    /// The metadata of one entry per line, as JSON
+    Jsonl,
+    /// Tab separated key, year, authors, and title
+    Tsv,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Key,
+    Year,
+    /// The first author's family name
+    Author,
+    Title,
+    /// When the entry was added to the library
+    Added,
+}
+
+/// An entry in the library, with the values that listings are built from
+pub struct Item {
+    pub key: String,
+    pub entry: Entry,
+}
+
+/// Formats a timestamp as a date in the local timezone
+fn local_date(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+impl Item {
+    fn get_str(&self, field: &str) -> Option<&str> {
+        self.entry.data.fields.get(field).and_then(|v| v.as_str())
+    }
+
+    /// The value of one of the `FIELDS`, on a single line
+    pub fn field(&self, field: &str) -> String {
+        let value = match field {
+            "key" => Some(self.key.clone()),
+            "year" => self.entry.data.year().map(|y| y.to_string()),
+            "author" => self
+                .entry
+                .data
+                .fields
+                .get("author")
+                .and_then(|a| display_list(&parse_names(a))),
+            "title" => self.get_str("title").map(|s| s.to_string()),
+            "type" => self.get_str("type").map(|s| s.to_string()),
+            "container" => self.get_str("container-title").map(|s| s.to_string()),
+            "doi" => self.get_str("DOI").map(|s| s.to_string()),
+            "tags" => Some(self.entry.data.tags.join(", ")),
+            "added" => self.entry.data.added.as_ref().map(local_date),
+            "modified" => self.entry.data.modified.as_ref().map(local_date),
+            "status" => self.entry.data.status.map(|s| s.to_string()),
+            "rating" => self.entry.data.rating.map(|r| r.to_string()),
+            _ => None,
+        };
+        value
+            .map(|v| v.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default()
+    }
+
+    fn first_author(&self) -> Option<String> {
+        let authors = parse_names(self.entry.data.fields.get("author")?);
+        Some(authors.first()?.key_part().to_lowercase())
+    }
+
+    /// The entry's metadata, with its key
+    fn to_json(&self) -> Result<Value> {
+        let mut value = serde_json::to_value(&self.entry.data)?;
+        if let Value::Object(map) = &mut value {
+            map.insert("key".to_string(), self.key.clone().into());
+        }
+        Ok(value)
+    }
+}
+
+/// Sorts the items, which are ordered by key within equal values.
+/// Entries that do not have a value come last, even when the order is reversed.
+pub fn sort(items: &mut [Item], by: SortBy, reverse: bool) {
+    fn compare<T: Ord>(a: Option<T>, b: Option<T>, reverse: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) if reverse => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }
+    }
+    let title = |item: &Item| item.get_str("title").map(|t| t.to_lowercase());
+    items.sort_by(|a, b| a.key.cmp(&b.key));
+    match by {
+        SortBy::Key if reverse => items.reverse(),
+        SortBy::Key => {}
+        SortBy::Year => {
+            items.sort_by(|a, b| compare(a.entry.data.year(), b.entry.data.year(), reverse))
+        }
+        SortBy::Author => {
+            items.sort_by(|a, b| compare(a.first_author(), b.first_author(), reverse))
+        }
+        SortBy::Title => items.sort_by(|a, b| compare(title(a), title(b), reverse)),
+        SortBy::Added => {
+            items.sort_by(|a, b| compare(a.entry.data.added, b.entry.data.added, reverse))
+        }
+    }
+}
+
+/// A `--template`, e.g. "{key}\t{year}\t{title}"
+pub struct Template(String);
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        for caps in PLACEHOLDER_RE.captures_iter(template) {
+            if !FIELDS.contains(&&caps[1]) {
+                anyhow::bail!(
+                    "Unknown field in template: {{{}}} (expected one of: {})",
+                    &caps[1],
+                    FIELDS.join(", ")
+                );
+            }
+        }
+        // Allow escapes, since they are awkward to type in a shell
+        Ok(Self(template.replace("\\t", "\t").replace("\\n", "\n")))
+    }
+
+    pub fn render(&self, item: &Item) -> String {
+        PLACEHOLDER_RE
+            .replace_all(&self.0, |caps: &regex::Captures| item.field(&caps[1]))
+            .into_owned()
+    }
+}
+
+/// Truncates `s` to at most `width` characters, marking that it was cut off
+fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_string();
+    }
+    let mut truncated: String = s.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Renders the items as aligned columns with a header, one string per line.
+/// Long authors and titles are truncated.
+pub fn table(items: &[Item], columns: &[&str]) -> Vec<String> {
+    let max_width = |column: &str| match column {
+        "author" => 30,
+        "title" => 80,
+        _ => usize::MAX,
+    };
+    let rows: Vec<Vec<String>> = items
+        .iter()
+        .map(|i| {
+            columns
+                .iter()
+                .map(|c| truncate(&i.field(c), max_width(c)))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|c| {
+            rows.iter()
+                .map(|r| r[c].chars().count())
+                .chain([columns[c].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line = |row: &[String]| {
+        row.iter()
+            .zip(widths.iter())
+            .map(|(v, &width)| format!("{v:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    std::iter::once(line(&header))
+        .chain(rows.iter().map(|r| line(r)))
+        .collect()
+}
+
+/// Renders the items in a format, one string per line
+pub fn render(items: &[Item], format: Format) -> Result<Vec<String>> {
+    const COLUMNS: [&str; 4] = ["key", "year", "author", "title"];
+    Ok(match format {
+        Format::Keys => items.iter().map(|i| i.key.clone()).collect(),
+        Format::Json => {
+            let values = items
+                .iter()
+                .map(Item::to_json)
+                .collect::<Result<Vec<_>>>()?;
+            vec![serde_json::to_string_pretty(&values)?]
+        }
+        Format::Jsonl => items
+            .iter()
+            .map(|i| Ok(serde_json::to_string(&i.to_json()?)?))
+            .collect::<Result<_>>()?,
+        Format::Tsv => items
+            .iter()
+            .map(|i| COLUMNS.map(|c| i.field(c)).join("\t"))
+            .collect(),
+        Format::Table => table(items, &COLUMNS),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::entry::EntryData;
+
+    fn item(key: &str, fields: Value) -> Item {
+        let data = EntryData {
+            fields: serde_json::from_value(fields).unwrap(),
+            ..Default::default()
+        };
+        Item {
+            key: key.to_string(),
+            entry: Entry { source: None, data },
+        }
+    }
+
+    fn keys(items: &[Item]) -> Vec<&str> {
+        items.iter().map(|i| i.key.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_missing_values_last() {
+        let mut items = vec![
+            item("c", json!({})),
+            item("b", json!({"issued": {"date-parts": [[2020]]}})),
+            item("a", json!({"issued": {"date-parts": [[2021]]}})),
+        ];
+        sort(&mut items, SortBy::Year, false);
+        assert_eq!(keys(&items), ["b", "a", "c"]);
+        sort(&mut items, SortBy::Year, true);
+        assert_eq!(keys(&items), ["a", "b", "c"]);
+        sort(&mut items, SortBy::Key, true);
+        assert_eq!(keys(&items), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn renders_templates() {
+        let item = item(
+            "doe_2020_title",
+            json!({
+                "title": "A\n  long title",
+                "author": [{"family": "Doe", "given": "Jane"}, {"literal": "ACME"}],
+                "issued": {"date-parts": [[2020, 1]]},
+            }),
+        );
+        let template = Template::parse(r"{key}\t{year}\t{author}\t{title}{doi}").unwrap();
+        assert_eq!(
+            template.render(&item),
+            "doe_2020_title\t2020\tJane Doe & ACME\tA long title"
+        );
+        assert!(Template::parse("{key} {abstract}").is_err());
+    }
+}
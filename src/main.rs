@@ -1,20 +1,38 @@
+mod attachments;
 mod bibtex;
 mod citekey;
 mod config;
+mod doctor;
+mod doi;
+mod editor;
 mod entry;
 mod fetch;
+mod filter;
+mod http;
+mod list;
+mod markdown;
+mod names;
+mod notes;
 mod ops;
 mod pdf;
+mod pick;
+mod query;
 mod regex;
+mod stats;
+mod tags;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Write};
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand, ValueHint};
+use clap::{ArgAction, Parser, Subcommand, ValueHint};
 use itertools::Itertools;
 
-use self::entry::EntryData;
+use self::attachments::Role;
+use self::doi::Doi;
+use self::entry::{EntryData, ReadingStatus};
+use self::filter::Filters;
+use self::list::{Format, SortBy};
 use self::ops::read_entry;
 
 #[derive(Parser, Debug)]
@@ -30,45 +48,89 @@ struct CliArgs {
     /// Path to the root of the library to use (defaults to path specified in config)
     #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
     pub root: Option<PathBuf>,
+
+    /// Do not access the network (API responses are served from the cache when possible)
+    #[arg(long, global = true, default_value_t = false)]
+    pub offline: bool,
 }
 
 #[derive(Subcommand, Debug)]
 enum TagsCommand {
     /// List all of the tags the currently exist in your library
     #[clap(alias = "ls")]
-    List,
+    List {
+        /// Show nested tags (e.g. `project/grant-2025`) as a tree, with the number of entries
+        #[arg(long, default_value_t = false)]
+        tree: bool,
+
+        /// Show the description of each tag from the `tag_vocabulary`
+        #[arg(short, long, conflicts_with = "tree", default_value_t = false)]
+        describe: bool,
+    },
+
+    /// Rename a tag (and the tags beneath it) in every entry
+    Rename {
+        /// The current name of the tag
+        old: String,
+
+        /// The new name of the tag
+        new: String,
+    },
+
+    /// Replace several tags (and the tags beneath them) with a single tag in every entry
+    Merge {
+        /// The tags to merge
+        #[arg(num_args(1..), required = true)]
+        tags: Vec<String>,
+
+        /// The tag that replaces them
+        #[arg(long)]
+        into: String,
+    },
 
     /// Add a tag to an entry
     Add {
-        /// The tag(s) to be added
-        #[arg(short, long, num_args(1..))]
+        /// The tag to be added (repeat to add several, e.g. `-t a -t b`)
+        #[arg(short, long, action = ArgAction::Append, value_parser = tags::parse_tag)]
         tags: Vec<String>,
 
         /// The citekey(s) of the entri(es) to add the tag to.
         #[arg(short, long, num_args(1..))]
         keys: Vec<String>,
 
+        /// Add the tag(s) to the entries in these collections
+        #[arg(value_name = "@COLLECTION", value_parser = filter::parse_collection)]
+        collections: Vec<String>,
+
         /// Add the tag(s) to all entries in your library
-        #[arg(long, conflicts_with = "keys", default_value_t = false)]
+        #[arg(long, conflicts_with_all = ["keys", "collections"], default_value_t = false)]
         all_entries: bool,
     },
 
     /// Remove a tag from an entry
     Remove {
-        /// The tag(s) to be removed
-        #[arg(short, long, num_args(1..))]
+        /// The tag to be removed (repeat to remove several, e.g. `-t a -t b`)
+        #[arg(short, long, action = ArgAction::Append, value_parser = tags::parse_tag)]
         tags: Vec<String>,
 
         /// Remove all tags from the entries with
         #[arg(long, conflicts_with = "tags", default_value_t = false)]
         all_tags: bool,
 
+        /// Also remove the tags beneath the given tag(s) (e.g. `project/grant-2025` for `project`)
+        #[arg(short, long, conflicts_with = "all_tags", default_value_t = false)]
+        recursive: bool,
+
         /// The citekey(s) of the entri(es) to remove the tag from
         #[arg(short, long, num_args(1..))]
         keys: Vec<String>,
 
+        /// Remove the tag(s) from the entries in these collections
+        #[arg(value_name = "@COLLECTION", value_parser = filter::parse_collection)]
+        collections: Vec<String>,
+
         /// Remove the tag(s) from all entries in your library
-        #[arg(long, conflicts_with = "keys", default_value_t = false)]
+        #[arg(long, conflicts_with_all = ["keys", "collections"], default_value_t = false)]
         all_entries: bool,
     },
 }
@@ -81,24 +143,104 @@ enum Command {
     /// List the entries in your library
     #[clap(alias = "ls")]
     List {
-        /// List entries with a specific tag
-        #[arg(long, num_args(1..), conflicts_with = "all_tags")]
-        any_tag: Option<Vec<String>>,
+        #[command(flatten)]
+        filters: Filters,
+
+        /// How to print the entries
+        #[arg(long, value_enum, default_value_t = Format::Keys, conflicts_with = "template")]
+        format: Format,
+
+        /// Print each entry by filling in a template, e.g. '{key}\t{year}\t{author}\t{title}'
+        /// (fields: key, year, author, title, type, container, doi, tags, added, modified, status,
+        /// rating)
+        #[arg(long)]
+        template: Option<String>,
+
+        /// The order to list the entries in
+        #[arg(long, value_enum, default_value_t = SortBy::Key)]
+        sort: SortBy,
+
+        /// Reverse the order
+        #[arg(long, default_value_t = false)]
+        reverse: bool,
+    },
+
+    /// Summarize your library (counts by year, type, source, tag, author...)
+    Stats {
+        #[command(flatten)]
+        filters: Filters,
 
-        #[arg(long, num_args(1..), conflicts_with = "any_tag")]
-        all_tags: Option<Vec<String>>,
+        /// Print the statistics as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+
+        /// How many of the most common container titles and authors to show
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Search for references interactively and print the keys of the ones you pick
+    /// (e.g. `picoref to-bibtex $(picoref pick)`)
+    Pick {
+        #[command(flatten)]
+        filters: Filters,
     },
 
     /// Add a reference to your library
     Add {
-        /// The DOI of the reference to fetch
+        /// The DOI of the reference to fetch (or the URL of a preprint on a supported server)
         doi: String,
 
         /// Tags to add to the new entry
-        #[arg(short, long, num_args(1..))]
+        #[arg(short, long, num_args(1..), value_parser = tags::parse_tag)]
         tags: Option<Vec<String>>,
     },
 
+    /// Show or set how far along you are in reading a reference
+    Status {
+        /// The citekey of the entry
+        key: String,
+
+        /// The new status (if not provided, the current status is printed)
+        #[arg(value_enum, conflicts_with = "clear")]
+        status: Option<ReadingStatus>,
+
+        /// Remove the entry's status
+        #[arg(long, default_value_t = false)]
+        clear: bool,
+    },
+
+    /// Show or set your rating of a reference
+    Rate {
+        /// The citekey of the entry
+        key: String,
+
+        /// The new rating, from 1 to 5 (if not provided, the current rating is printed)
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=5), conflicts_with = "clear")]
+        rating: Option<u8>,
+
+        /// Remove the entry's rating
+        #[arg(long, default_value_t = false)]
+        clear: bool,
+    },
+
+    /// List the references you are reading or have yet to read, from the earliest added
+    Queue {
+        /// Print each entry by filling in a template (see `list --template`)
+        #[arg(long)]
+        template: Option<String>,
+    },
+
+    /// Replace the metadata of preprints that have since been published with the published version's
+    Upgrade {
+        /// The citekey(s) of the entri(es) (or @collections) to check (defaults to all entries)
+        keys: Vec<String>,
+
+        /// Only list the entries that have a published version
+        #[arg(short = 'n', long, default_value_t = false)]
+        dry_run: bool,
+    },
+
     /// Add a pdf to an entry in your library
     Pdf {
         /// The citekey of the reference to fetch
@@ -108,6 +250,45 @@ enum Command {
         file: Option<PathBuf>,
     },
 
+    /// Copy a file (e.g. supplementary material) into an entry in your library
+    Attach {
+        /// The citekey of the entry to attach the file to
+        key: String,
+
+        /// The file to attach
+        #[arg(value_name = "FILE", value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+
+        /// What the file is
+        #[arg(long, value_enum, default_value_t = Role::Supplement)]
+        role: Role,
+
+        /// Where the file was obtained from
+        #[arg(long, value_name = "URL")]
+        source_url: Option<String>,
+    },
+
+    /// List the files attached to an entry
+    Attachments {
+        /// The citekey of the entry
+        key: String,
+    },
+
+    /// Delete entries (including their PDFs, attachments, and notes) from your library
+    #[clap(alias = "rm")]
+    Remove {
+        /// The citekey(s) of the entri(es) (or @collections) to remove
+        #[arg(num_args(1..), required = true)]
+        keys: Vec<String>,
+
+        /// Do not ask for confirmation
+        #[arg(short, long, default_value_t = false)]
+        yes: bool,
+    },
+
+    /// Check your library for problems (invalid metadata, missing or untracked files)
+    Doctor,
+
     /// Generate a markdown file with the entry's metadata
     #[clap(alias = "md")]
     Markdown {
@@ -115,6 +296,18 @@ enum Command {
         key: String,
     },
 
+    /// Open an entry's notes in your editor (creating them from a template if needed)
+    Note {
+        /// The citekey of the entry
+        key: String,
+    },
+
+    /// Edit an entry's metadata in your editor (the changes are only saved if they are valid CSL)
+    Edit {
+        /// The citekey of the entry to edit
+        key: String,
+    },
+
     /// Import all entries from a json file
     Import {
         /// The file to import from
@@ -132,6 +325,10 @@ enum Command {
         /// The citekey of the reference to export (if not provided, all entries are exported)
         #[arg(short, long)]
         key: Option<String>,
+
+        /// The citekeys of the references (or @collections) to export
+        #[arg(conflicts_with = "key")]
+        keys: Vec<String>,
     },
 
     /// Export entries as json
@@ -143,6 +340,10 @@ enum Command {
         /// The citekey of the reference to export (if not provided, all entries are exported)
         #[arg(short, long)]
         key: Option<String>,
+
+        /// The citekeys of the references (or @collections) to export
+        #[arg(conflicts_with = "key")]
+        keys: Vec<String>,
     },
 
     /// Modify (add / remove) or list tags
@@ -155,70 +356,173 @@ enum Command {
 fn main() -> Result<()> {
     let cli_args = CliArgs::parse();
     let conf = config::load(cli_args.config)?;
-    let root = cli_args.root.unwrap_or(conf.root);
+    http::init(&conf, cli_args.offline)?;
+    let root = cli_args.root.unwrap_or_else(|| conf.root.clone());
+    let vocabulary = conf
+        .tag_vocabulary
+        .as_deref()
+        .map(tags::Vocabulary::load)
+        .transpose()?;
 
     match cli_args.command {
         Command::Root => println!("{}", root.to_str().expect("path to be valid unicode")),
-        Command::ToBibtex { path, key } => {
-            let paths = if let Some(k) = key {
-                vec![ops::entry_root_path(&root, &k)]
-            } else {
+        Command::ToBibtex { path, key, keys } => {
+            let keys: Vec<String> = key.into_iter().chain(keys).collect();
+            let paths = if keys.is_empty() {
                 ops::all_entry_paths(&root)?
+            } else {
+                filter::expand_keys(&keys, &root, &conf)?
+                    .iter()
+                    .map(|k| ops::entry_root_path(&root, k))
+                    .collect()
             };
             let content = paths
                 .into_iter()
                 .map(|p| {
-                    read_entry(&ops::data_path(
-                        &root,
-                        p.file_name()
-                            .context("Not a valid file name")?
-                            .to_str()
-                            .context("Path contains unicode")?,
-                    ))
-                    .map(|e| e.data)
+                    let key = p
+                        .file_name()
+                        .context("Not a valid file name")?
+                        .to_str()
+                        .context("Path contains unicode")?
+                        .to_string();
+                    let data = read_entry(&ops::data_path(&root, &key))?.data;
+                    Ok((key, data))
                 })
-                .collect::<Result<Vec<EntryData>>>()?;
+                .collect::<Result<Vec<(String, EntryData)>>>()?;
             let mut file = File::create(path)?;
-            file.write_all(bibtex::to_bibtex(content)?.as_bytes())?;
+            file.write_all(bibtex::to_bibtex(&root, content)?.as_bytes())?;
         }
-        Command::List { any_tag, all_tags } => {
-            let mut paths = ops::all_entry_paths(&root)?;
-            if any_tag.is_some() || all_tags.is_some() {
-                paths.retain(|p| {
-                    let entry_tags = ops::read_entry(&ops::data_path(
-                        &root,
-                        &p.file_name().unwrap().to_string_lossy(),
-                    ))
-                    .unwrap()
-                    .data
-                    .tags;
-                    match (&any_tag, &all_tags) {
-                        (Some(tags), None) => tags.iter().any(|t| entry_tags.contains(t)),
-                        (None, Some(tags)) => tags.iter().all(|t| entry_tags.contains(t)),
-                        _ => unreachable!(),
-                    }
+        Command::List {
+            filters,
+            format,
+            template,
+            sort,
+            reverse,
+        } => {
+            let template = template.as_deref().map(list::Template::parse).transpose()?;
+            let mut items: Vec<_> = filters
+                .select(&root, &conf)?
+                .into_iter()
+                .map(|(key, entry)| list::Item { key, entry })
+                .collect();
+            list::sort(&mut items, sort, reverse);
+            let lines = match template {
+                Some(template) => items.iter().map(|i| template.render(i)).collect(),
+                None => list::render(&items, format)?,
+            };
+            let mut writer = BufWriter::new(std::io::stdout().lock());
+            for line in lines {
+                writeln!(writer, "{line}")?;
+            }
+            writer.flush()?;
+        }
+        Command::Status { key, status, clear } => {
+            let mut entry = read_entry(&ops::data_path(&root, &key))?;
+            if status.is_none() && !clear {
+                if let Some(status) = entry.data.status {
+                    println!("{status}");
+                }
+                return Ok(());
+            }
+            entry.data.status = status;
+            ops::write_entry(&root, &key, &mut entry.data, true)?;
+        }
+        Command::Rate { key, rating, clear } => {
+            let mut entry = read_entry(&ops::data_path(&root, &key))?;
+            if rating.is_none() && !clear {
+                if let Some(rating) = entry.data.rating {
+                    println!("{rating}");
+                }
+                return Ok(());
+            }
+            entry.data.rating = rating;
+            ops::write_entry(&root, &key, &mut entry.data, true)?;
+        }
+        Command::Queue { template } => {
+            let template = template.as_deref().map(list::Template::parse).transpose()?;
+            let mut items: Vec<_> = ops::all_keys(&root)?
+                .into_iter()
+                .map(|key| {
+                    let entry = read_entry(&ops::data_path(&root, &key))?;
+                    Ok(list::Item { key, entry })
+                })
+                .filter_ok(|i| {
+                    matches!(
+                        i.entry.data.status,
+                        Some(ReadingStatus::Unread | ReadingStatus::Reading)
+                    )
                 })
+                .collect::<Result<_>>()?;
+            list::sort(&mut items, SortBy::Added, false);
+            let lines = match template {
+                Some(template) => items.iter().map(|i| template.render(i)).collect(),
+                None => list::table(&items, &["key", "status", "added", "title"]),
+            };
+            for line in lines {
+                println!("{line}");
             }
-            for path in paths {
-                println!("{}", ops::key_from_path(&path))
+        }
+        Command::Pick { filters } => {
+            let mut items: Vec<_> = filters
+                .select(&root, &conf)?
+                .into_iter()
+                .map(|(key, entry)| list::Item { key, entry })
+                .collect();
+            list::sort(&mut items, SortBy::Added, true);
+            for key in pick::pick(&root, vocabulary.as_ref(), items)? {
+                println!("{key}");
+            }
+        }
+        Command::Stats { filters, json, top } => {
+            let stats = stats::compute(&root, &filters.select(&root, &conf)?, top);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print!("{stats}");
             }
         }
-        Command::Add { doi, tags } => {
-            let mut entry = fetch::fetch_metadata(&doi)?;
+        Command::Add { doi: input, tags } => {
+            let doi = match Doi::parse(&input) {
+                Ok(doi) => doi,
+                Err(e) => match fetch::doi_from_url(&input)? {
+                    Some(doi) => Doi::parse(&doi)?,
+                    None => return Err(e),
+                },
+            };
+            let index = ops::doi_index(&root)?;
+            if let Some(existing) = index.get(&doi) {
+                println!("{doi} is already in your library as {existing} (skipping)");
+                return Ok(());
+            }
+            let mut entry = fetch::fetch_metadata(&doi, &conf)?;
             let key = citekey::get_key(&entry.data)?;
             ops::update_metadata(&mut entry.data, &key)?;
+            // The DOI of the published version may differ from the one that was provided
+            let fetched_doi = entry
+                .data
+                .fields
+                .get("DOI")
+                .and_then(|d| Doi::parse(d.as_str()?).ok());
+            if let Some(fetched_doi) = fetched_doi {
+                if let Some(existing) = index.get(&fetched_doi) {
+                    println!("{fetched_doi} is already in your library as {existing} (skipping)");
+                    return Ok(());
+                }
+            }
             if let Some(t) = tags {
+                if let Some(vocabulary) = &vocabulary {
+                    vocabulary.warn_unknown(&t);
+                }
                 entry.data.tags.extend(t);
             }
-            ops::write_entry(&root, &key, &entry.data, false)?;
+            ops::write_entry(&root, &key, &mut entry.data, false)?;
             println!("{key}");
             if conf.fetch_pdf_on_add {
                 if let Some(ref source) = entry.source {
                     let pdf_path = ops::pdf_path(&root, &key);
                     if !pdf_path.exists() {
-                        let title = entry.data.fields.get("title").and_then(|t| t.as_str());
                         if let Err(e) =
-                            pdf::download_pdf(source, &conf.email, title, &key, &pdf_path)
+                            pdf::download_pdf(source, &conf, &entry.data, &key, &pdf_path)
                         {
                             eprintln!("Could not automatically fetch PDF: {e}");
                         }
@@ -227,55 +531,93 @@ fn main() -> Result<()> {
             }
         }
         Command::Tags { action } => match &action {
-            TagsCommand::List => ops::all_entry_paths(&root)?
-                .into_iter()
-                .flat_map(|p| {
-                    ops::read_entry(&ops::data_path(
-                        &root,
-                        &p.file_name().unwrap().to_string_lossy(),
-                    ))
-                    .unwrap()
-                    .data
-                    .tags
-                })
-                .unique()
-                .sorted()
-                .for_each(|t| println!("{t}")),
+            TagsCommand::List {
+                tree: false,
+                describe,
+            } => {
+                let entry_tags = ops::all_keys(&root)?
+                    .iter()
+                    .map(|key| Ok(read_entry(&ops::data_path(&root, key))?.data.tags))
+                    .collect::<Result<Vec<_>>>()?;
+                for t in entry_tags.into_iter().flatten().unique().sorted() {
+                    let description = vocabulary.as_ref().and_then(|v| v.description(&t));
+                    match description {
+                        Some(d) if *describe => println!("{t}\t{d}"),
+                        _ => println!("{t}"),
+                    }
+                }
+            }
+            TagsCommand::List { tree: true, .. } => {
+                let entry_tags = ops::all_keys(&root)?
+                    .iter()
+                    .map(|key| Ok(read_entry(&ops::data_path(&root, key))?.data.tags))
+                    .collect::<Result<Vec<_>>>()?;
+                for node in tags::tree(entry_tags.iter().map(|t| t.as_slice())) {
+                    println!("{}{} ({})", "  ".repeat(node.depth), node.name, node.count);
+                }
+            }
             TagsCommand::Add {
                 tags,
                 keys,
+                collections,
                 all_entries,
             } => {
+                if let Some(vocabulary) = &vocabulary {
+                    vocabulary.warn_unknown(tags);
+                }
                 let keys = if *all_entries {
                     ops::all_keys(&root)?
                 } else {
-                    keys.to_vec()
+                    filter::expand_keys(&[keys.as_slice(), collections].concat(), &root, &conf)?
                 };
                 for key in keys.iter() {
                     let mut entry = ops::read_entry(&ops::data_path(&root, key))?;
                     entry.data.tags.extend(tags.clone());
-                    ops::write_entry(&root, key, &entry.data, true)?;
+                    ops::write_entry(&root, key, &mut entry.data, true)?;
+                }
+            }
+            TagsCommand::Rename { old, new } => {
+                if let Some(vocabulary) = &vocabulary {
+                    vocabulary.warn_unknown([new]);
+                }
+                for key in tags::rename_all(&root, std::slice::from_ref(old), new)? {
+                    println!("{key}");
+                }
+            }
+            TagsCommand::Merge { tags, into } => {
+                if let Some(vocabulary) = &vocabulary {
+                    vocabulary.warn_unknown([into]);
+                }
+                for key in tags::rename_all(&root, tags, into)? {
+                    println!("{key}");
                 }
             }
             TagsCommand::Remove {
                 tags,
                 all_tags,
+                recursive,
                 keys,
+                collections,
                 all_entries,
             } => {
                 let keys = if *all_entries {
                     ops::all_keys(&root)?
                 } else {
-                    keys.to_vec()
+                    filter::expand_keys(&[keys.as_slice(), collections].concat(), &root, &conf)?
                 };
                 for key in keys.iter() {
                     let mut entry = ops::read_entry(&ops::data_path(&root, key))?;
                     if *all_tags {
                         entry.data.tags.clear();
+                    } else if *recursive {
+                        entry
+                            .data
+                            .tags
+                            .retain(|t| !tags.iter().any(|r| tags::is_within(t, r)));
                     } else {
                         entry.data.tags.retain(|t| !tags.contains(t));
                     }
-                    ops::write_entry(&root, key, &entry.data, true)?;
+                    ops::write_entry(&root, key, &mut entry.data, true)?;
                 }
             }
         },
@@ -283,17 +625,33 @@ fn main() -> Result<()> {
             let file = File::open(path)?;
             let reader = BufReader::new(file);
             let mut entries: Vec<EntryData> = serde_json::from_reader(reader)?;
+            let mut index = ops::doi_index(&root)?;
             for data in entries.iter_mut() {
                 let key = citekey::get_key(data)?;
-                ops::update_metadata(data, &key)?;
+                if let Err(e) = ops::update_metadata(data, &key) {
+                    eprintln!("{key}: {e:#} (skipping)");
+                    continue;
+                }
+                if let Some(doi) = data.fields.get("DOI").and_then(|d| d.as_str()) {
+                    let doi = Doi::parse(doi)?;
+                    if let Some(existing) = index.get(&doi) {
+                        println!("{doi} is already in your library as {existing} (skipping)");
+                        continue;
+                    }
+                    index.insert(doi, key.clone());
+                }
                 ops::write_entry(&root, &key, data, false)?;
             }
         }
-        Command::ToJson { path, key } => {
-            let paths = if let Some(k) = key {
-                vec![ops::entry_root_path(&root, &k)]
-            } else {
+        Command::ToJson { path, key, keys } => {
+            let keys: Vec<String> = key.into_iter().chain(keys).collect();
+            let paths = if keys.is_empty() {
                 ops::all_entry_paths(&root)?
+            } else {
+                filter::expand_keys(&keys, &root, &conf)?
+                    .iter()
+                    .map(|k| ops::entry_root_path(&root, k))
+                    .collect()
             };
             let content = paths
                 .into_iter()
@@ -312,6 +670,59 @@ fn main() -> Result<()> {
             let writer = BufWriter::new(file);
             serde_json::to_writer(writer, &content)?;
         }
+        Command::Upgrade { keys, dry_run } => {
+            let keys = if keys.is_empty() {
+                ops::all_keys(&root)?
+            } else {
+                filter::expand_keys(&keys, &root, &conf)?
+            };
+            let index = ops::doi_index(&root)?;
+            for key in keys.iter().sorted() {
+                let entry = read_entry(&ops::data_path(&root, key))?;
+                let Some(doi) = entry.data.fields.get("DOI").and_then(|d| d.as_str()) else {
+                    continue;
+                };
+                let doi = match Doi::parse(doi) {
+                    Ok(doi) => doi,
+                    Err(e) => {
+                        eprintln!("{key}: {e:#}");
+                        continue;
+                    }
+                };
+                let published = match fetch::published_doi(&doi, &conf) {
+                    Ok(Some(published)) if published != doi => published,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        eprintln!("{key}: Could not check for a published version: {e:#}");
+                        continue;
+                    }
+                };
+                println!("{key}: {doi} -> {published}");
+                if let Some(existing) = index.get(&published).filter(|k| *k != key) {
+                    eprintln!("{key}: {published} is already in your library as {existing}");
+                    continue;
+                }
+                if dry_run {
+                    continue;
+                }
+                let mut data = match fetch::fetch_metadata(&published, &conf) {
+                    Ok(upgraded) => upgraded.data,
+                    Err(e) => {
+                        eprintln!("{key}: {e:#}");
+                        continue;
+                    }
+                };
+                // Keep the key (so that citations still work) and everything picoref manages
+                if let Err(e) = ops::update_metadata(&mut data, key) {
+                    eprintln!("{key}: {e:#}");
+                    continue;
+                }
+                data.keep_managed_fields(entry.data);
+                data.fields
+                    .insert("preprint-DOI".to_string(), doi.to_string().into());
+                ops::write_entry(&root, key, &mut data, true)?;
+            }
+        }
         Command::Pdf { key, file } => {
             let entry = read_entry(&ops::data_path(&root, &key))?;
             let path = ops::pdf_path(&root, &key);
@@ -321,86 +732,176 @@ fn main() -> Result<()> {
             if let Some(source_path) = file {
                 std::fs::copy(source_path, path)?;
             } else {
-                let title = entry.data.fields.get("title").and_then(|t| t.as_str());
                 pdf::download_pdf(
-                    &entry.source.expect("Cannot fetch PDF for reference with no DOI"),
-                    &conf.email,
-                    title,
+                    entry
+                        .source
+                        .as_ref()
+                        .expect("Cannot fetch PDF for reference with no DOI"),
+                    &conf,
+                    &entry.data,
                     &key,
                     &path,
                 )?;
             };
         }
-        Command::Markdown { key } => {
-            let data = read_entry(&ops::data_path(&root, &key))?.data;
-            let stdout = std::io::stdout().lock();
-            let mut writer = BufWriter::new(stdout);
-
-            if let Some(title) = data.fields.get("title").and_then(|t| t.as_str()) {
-                writer.write_all("# ".as_bytes())?;
-                writer.write_all(title.as_bytes())?;
-                writer.write_all("\n".as_bytes())?;
+        Command::Attach {
+            key,
+            file,
+            role,
+            source_url,
+        } => {
+            let mut entry = read_entry(&ops::data_path(&root, &key))?;
+            let attachment = attachments::attach(&root, &key, &file, role, source_url)?;
+            println!("{}", attachment.filename);
+            entry.data.attachments.push(attachment);
+            ops::write_entry(&root, &key, &mut entry.data, true)?;
+        }
+        Command::Attachments { key } => {
+            let entry = read_entry(&ops::data_path(&root, &key))?;
+            let pdf_path = ops::pdf_path(&root, &key);
+            if pdf_path.exists() {
+                println!("pdf\t{key}.pdf\tapplication/pdf\t");
             }
-
-            let by_line = data
-                .fields
-                .get("author")
-                .and_then(|a| {
-                    a.as_array()?
-                        .iter()
-                        .map(|a| {
-                            let given = a.get("given")?.as_str()?;
-                            let family = a.get("family")?.as_str()?;
-                            Some(format!("{given} {family}"))
-                        })
-                        .collect::<Option<Vec<String>>>()
-                })
-                .map(|mut v| {
-                    if v.len() > 1 {
-                        let last_i = v.len() - 1;
-                        let new_last_entry = format!("& {}", v[last_i]);
-                        let _ = std::mem::replace(&mut v[last_i], new_last_entry);
+            for a in entry.data.attachments {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    a.role,
+                    a.filename,
+                    a.mime_type,
+                    a.source_url.unwrap_or_default()
+                );
+            }
+        }
+        Command::Remove { keys, yes } => {
+            for key in filter::expand_keys(&keys, &root, &conf)?.iter() {
+                let dir = ops::entry_root_path(&root, key);
+                if !ops::data_path(&root, key).exists() {
+                    anyhow::bail!("No entry found at: {}", dir.to_string_lossy());
+                }
+                if !yes {
+                    eprintln!("The following files will be deleted:");
+                    for file in std::fs::read_dir(&dir)? {
+                        eprintln!("  {}", file?.path().to_string_lossy());
                     }
-                    v.join(if v.len() > 2 { ", " } else { " " })
-                })
-                .or_else(|| Some(data.fields.get("source")?.as_str()?.to_string()));
-            if let Some(by) = &by_line {
-                writer.write_all(by.as_bytes())?;
-                writer.write_all("\n".as_bytes())?;
+                    if !editor::confirm(&format!("Remove {key}?"), false)? {
+                        continue;
+                    }
+                }
+                std::fs::remove_dir_all(dir)?;
             }
-
-            if let Some(container) = data.fields.get("container-title").and_then(|c| c.as_str()) {
-                writer.write_all("*".as_bytes())?;
-                writer.write_all(container.as_bytes())?;
-                writer.write_all("*".as_bytes())?;
+        }
+        Command::Doctor => {
+            let mut n_problems = 0;
+            for key in ops::all_keys(&root)?.iter().sorted() {
+                for problem in doctor::check_entry(&root, key, vocabulary.as_ref()) {
+                    println!("{key}: {problem}");
+                    n_problems += 1;
+                }
             }
-
-            if let Some(year) = data
-                .fields
-                .get("issued")
-                .and_then(|i| i.get("date-parts")?.get(0)?.get(0)?.as_u64())
-            {
-                writer.write_all(" (".as_bytes())?;
-                writer.write_all(year.to_string().as_bytes())?;
-                writer.write_all(")".as_bytes())?;
+            if n_problems > 0 {
+                anyhow::bail!("Found {n_problems} problem(s)");
             }
-
-            writer.write_all("\n\n".as_bytes())?;
-
-            if let Some(entry_abstract) = data.fields.get("abstract").and_then(|a| a.as_str()) {
-                writer.write_all(entry_abstract.as_bytes())?;
-                writer.write_all("\n\n".as_bytes())?;
+            println!("No problems found");
+        }
+        Command::Note { key } => {
+            let data = read_entry(&ops::data_path(&root, &key))?.data;
+            notes::create_notes(&root, &key, &data, conf.notes_template.as_deref())?;
+            editor::open(&ops::notes_path(&root, &key))?;
+        }
+        Command::Edit { key } => {
+            let path = ops::data_path(&root, &key);
+            if !path.exists() {
+                anyhow::bail!("No entry found at: {}", path.to_string_lossy());
             }
+            // Edits are made to a copy so that the library never contains a broken file.
+            // The copy gets a unique name and is created exclusively, since the temp dir is shared.
+            let edit_file = tempfile::Builder::new()
+                .prefix(&format!("picoref-{key}-"))
+                .suffix(".toml")
+                .tempfile()?;
+            let edit_path = edit_file.path();
+            std::fs::copy(&path, edit_path)?;
+            let parse = |content: &str| -> Result<(EntryData, String)> {
+                let data = toml::from_str::<EntryData>(content)?;
+                data.validate()?;
+                let new_key = data
+                    .fields
+                    .get("citation-key")
+                    .and_then(|k| k.as_str())
+                    .unwrap_or(&key)
+                    .to_string();
+                if new_key != key {
+                    if new_key.is_empty() || new_key.contains(std::path::is_separator) {
+                        anyhow::bail!("Invalid citation-key: {new_key:?}");
+                    }
+                    if ops::entry_root_path(&root, &new_key).exists() {
+                        anyhow::bail!("An entry with the citation-key {new_key} already exists");
+                    }
+                }
+                Ok((data, new_key))
+            };
+            let (mut data, new_key) = loop {
+                editor::open(edit_path)?;
+                match parse(&std::fs::read_to_string(edit_path)?) {
+                    Ok(parsed) => break parsed,
+                    Err(e) => {
+                        eprintln!("{e:#}");
+                        if !editor::confirm("Reopen the editor to fix this?", true)? {
+                            anyhow::bail!(
+                                "Discarded changes, {} was not modified",
+                                path.to_string_lossy()
+                            );
+                        }
+                    }
+                }
+            };
+            edit_file.close()?;
 
-            let tags = data.tags;
-            if !tags.is_empty() {
-                writer.write_all("Tags: ".as_bytes())?;
-                writer.write_all(tags.join(", ").as_bytes())?;
-                writer.write_all("\n".as_bytes())?;
+            if new_key != key {
+                ops::move_entry(&root, &key, &new_key)?;
+                data.fields.insert("id".to_string(), new_key.clone().into());
+                println!("{new_key}");
             }
-
+            ops::write_entry(&root, &new_key, &mut data, true)?;
+        }
+        Command::Markdown { key } => {
+            let data = read_entry(&ops::data_path(&root, &key))?.data;
+            let stdout = std::io::stdout().lock();
+            let mut writer = BufWriter::new(stdout);
+            markdown::write_markdown(&data, &mut writer)?;
             writer.flush()?;
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags_add(args: &[&str]) -> (Vec<String>, Vec<String>) {
+        let args = CliArgs::try_parse_from(["picoref", "tags", "add"].iter().chain(args)).unwrap();
+        match args.command {
+            Command::Tags {
+                action:
+                    TagsCommand::Add {
+                        tags, collections, ..
+                    },
+            } => (tags, collections),
+            command => panic!("parsed as {command:?}"),
+        }
+    }
+
+    #[test]
+    fn tags_and_collections_in_any_order() {
+        let expected = (
+            vec!["x".to_string(), "y".to_string()],
+            vec!["@thesis".to_string()],
+        );
+        assert_eq!(tags_add(&["-t", "x", "-t", "y", "@thesis"]), expected);
+        assert_eq!(tags_add(&["@thesis", "-t", "x", "-t", "y"]), expected);
+        assert_eq!(tags_add(&["-t", "x", "@thesis", "-t", "y"]), expected);
+        assert!(CliArgs::try_parse_from(["picoref", "tags", "add", "-t", "@thesis"]).is_err());
+        assert!(CliArgs::try_parse_from(["picoref", "add", "10.1/x", "-t", "@thesis"]).is_err());
+    }
+}
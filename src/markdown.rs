@@ -0,0 +1,57 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::entry::EntryData;
+use crate::names;
+
+/// Renders the entry's metadata (title, authors, venue, abstract, and tags) as markdown
+pub fn write_markdown(data: &EntryData, writer: &mut impl Write) -> Result<()> {
+    if let Some(title) = data.fields.get("title").and_then(|t| t.as_str()) {
+        writer.write_all("# ".as_bytes())?;
+        writer.write_all(title.as_bytes())?;
+        writer.write_all("\n".as_bytes())?;
+    }
+
+    let by_line = data
+        .fields
+        .get("author")
+        .and_then(|a| names::display_list(&names::parse_names(a)))
+        .or_else(|| Some(data.fields.get("source")?.as_str()?.to_string()));
+    if let Some(by) = &by_line {
+        writer.write_all(by.as_bytes())?;
+        writer.write_all("\n".as_bytes())?;
+    }
+
+    if let Some(container) = data.fields.get("container-title").and_then(|c| c.as_str()) {
+        writer.write_all("*".as_bytes())?;
+        writer.write_all(container.as_bytes())?;
+        writer.write_all("*".as_bytes())?;
+    }
+
+    if let Some(year) = data
+        .fields
+        .get("issued")
+        .and_then(|i| i.get("date-parts")?.get(0)?.get(0)?.as_u64())
+    {
+        writer.write_all(" (".as_bytes())?;
+        writer.write_all(year.to_string().as_bytes())?;
+        writer.write_all(")".as_bytes())?;
+    }
+
+    writer.write_all("\n\n".as_bytes())?;
+
+    if let Some(entry_abstract) = data.fields.get("abstract").and_then(|a| a.as_str()) {
+        writer.write_all(entry_abstract.as_bytes())?;
+        writer.write_all("\n\n".as_bytes())?;
+    }
+
+    let tags = &data.tags;
+    if !tags.is_empty() {
+        writer.write_all("Tags: ".as_bytes())?;
+        writer.write_all(tags.join(", ").as_bytes())?;
+        writer.write_all("\n".as_bytes())?;
+    }
+
+    Ok(())
+}
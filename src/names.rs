@@ -0,0 +1,216 @@
+use biblatex::{Chunk, Chunks, Spanned};
+use serde_json::Value;
+
+/// A CSL name variable (e.g. a single `author` or `editor`).
+/// See https://citeproc-js.readthedocs.io/en/latest/csl-json/markup.html#name-fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Name {
+    /// A personal name, split into its parts
+    Person {
+        family: String,
+        given: Option<String>,
+        dropping_particle: Option<String>,
+        non_dropping_particle: Option<String>,
+        suffix: Option<String>,
+    },
+    /// A name that should not be split into parts, typically an institution or corporate author
+    Literal(String),
+}
+
+fn get_str(value: &Value, field: &str) -> Option<String> {
+    value
+        .get(field)?
+        .as_str()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+impl Name {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        if let Some(family) = get_str(value, "family") {
+            return Some(Self::Person {
+                family,
+                given: get_str(value, "given"),
+                dropping_particle: get_str(value, "dropping-particle"),
+                non_dropping_particle: get_str(value, "non-dropping-particle"),
+                suffix: get_str(value, "suffix"),
+            });
+        }
+        // CrossRef uses `name` rather than `literal` for organizations
+        get_str(value, "literal")
+            .or_else(|| get_str(value, "name"))
+            .or_else(|| get_str(value, "given"))
+            .map(Self::Literal)
+    }
+
+    /// The name as it would be written in running text (e.g. "Ludwig van Beethoven")
+    pub fn display(&self) -> String {
+        match self {
+            Self::Person {
+                family,
+                given,
+                dropping_particle,
+                non_dropping_particle,
+                suffix,
+            } => [
+                given.as_deref(),
+                dropping_particle.as_deref(),
+                non_dropping_particle.as_deref(),
+                Some(family.as_str()),
+                suffix.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" "),
+            Self::Literal(literal) => literal.clone(),
+        }
+    }
+
+    /// The part of the name that is used to build citekeys.
+    pub fn key_part(&self) -> String {
+        match self {
+            Self::Person {
+                family,
+                non_dropping_particle,
+                ..
+            } => match non_dropping_particle {
+                Some(particle) => format!("{particle} {family}"),
+                None => family.clone(),
+            },
+            Self::Literal(literal) => literal
+                .split_whitespace()
+                .last()
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// The name in BibLaTeX's "prefix Family, Suffix, Given" form.
+    /// Literal names are wrapped in braces so that BibLaTeX does not try to split them.
+    fn to_bib_chunks(&self) -> Chunks {
+        match self {
+            Self::Person {
+                family,
+                given,
+                dropping_particle,
+                non_dropping_particle,
+                suffix,
+            } => {
+                let mut s = [
+                    dropping_particle.as_deref(),
+                    non_dropping_particle.as_deref(),
+                ]
+                .into_iter()
+                .flatten()
+                .chain([family.as_str()])
+                .collect::<Vec<_>>()
+                .join(" ");
+                if let Some(suffix) = suffix {
+                    s.push_str(", ");
+                    s.push_str(suffix);
+                    // BibLaTeX requires the given name slot when a suffix is present
+                    s.push_str(", ");
+                    s.push_str(given.as_deref().unwrap_or_default());
+                } else if let Some(given) = given {
+                    s.push_str(", ");
+                    s.push_str(given);
+                }
+                vec![Spanned::detached(Chunk::Normal(s))]
+            }
+            Self::Literal(literal) => vec![Spanned::detached(Chunk::Verbatim(literal.clone()))],
+        }
+    }
+}
+
+/// Parses a CSL name list, skipping any names that are empty
+pub fn parse_names(value: &Value) -> Vec<Name> {
+    value
+        .as_array()
+        .map(|names| names.iter().filter_map(Name::from_value).collect())
+        .unwrap_or_default()
+}
+
+/// Formats a list of names as a BibLaTeX name list (separated by "and")
+pub fn to_bib_chunks(names: &[Name]) -> Chunks {
+    let mut chunks = Chunks::new();
+    for (i, name) in names.iter().enumerate() {
+        if i > 0 {
+            chunks.push(Spanned::detached(Chunk::Normal(" and ".to_string())));
+        }
+        chunks.extend(name.to_bib_chunks());
+    }
+    chunks
+}
+
+/// Formats a list of names for display (e.g. "A, B, & C")
+pub fn display_list(names: &[Name]) -> Option<String> {
+    let mut v: Vec<String> = names.iter().map(|n| n.display()).collect();
+    if v.is_empty() {
+        return None;
+    }
+    if v.len() > 1 {
+        let last_i = v.len() - 1;
+        let new_last_entry = format!("& {}", v[last_i]);
+        let _ = std::mem::replace(&mut v[last_i], new_last_entry);
+    }
+    Some(v.join(if v.len() > 2 { ", " } else { " " }))
+}
+
+#[cfg(test)]
+mod tests {
+    use biblatex::ChunksExt;
+    use serde_json::json;
+
+    use super::*;
+
+    fn names() -> Vec<Name> {
+        parse_names(&json!([
+            {"family": "Beethoven", "given": "Ludwig", "dropping-particle": "van"},
+            {"family": "King", "given": "Martin Luther", "suffix": "Jr."},
+            {"family": "Gogh", "given": "Vincent", "non-dropping-particle": "van"},
+            {"name": "World Health Organization"},
+            {"family": " ", "given": ""},
+        ]))
+    }
+
+    #[test]
+    fn parses_names() {
+        let names = names();
+        assert_eq!(names.len(), 4);
+        assert_eq!(
+            names[3],
+            Name::Literal("World Health Organization".to_string())
+        );
+    }
+
+    #[test]
+    fn bib_names() {
+        // As written in a field, so wrapped in braces
+        let bib = to_bib_chunks(&names()).to_biblatex_string(false);
+        assert_eq!(
+            bib,
+            "{van Beethoven, Ludwig and King, Jr., Martin Luther and van Gogh, Vincent and {World Health Organization}}"
+        );
+    }
+
+    #[test]
+    fn displays_names() {
+        let names = names();
+        assert_eq!(
+            display_list(&names).unwrap(),
+            "Ludwig van Beethoven, Martin Luther King Jr., Vincent van Gogh, & World Health Organization"
+        );
+        assert_eq!(
+            display_list(&names[..2]).unwrap(),
+            "Ludwig van Beethoven & Martin Luther King Jr."
+        );
+        assert_eq!(display_list(&[]), None);
+    }
+
+    #[test]
+    fn key_parts() {
+        let parts: Vec<String> = names().iter().map(|n| n.key_part()).collect();
+        assert_eq!(parts, ["Beethoven", "King", "van Gogh", "Organization"]);
+    }
+}
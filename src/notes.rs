@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::entry::EntryData;
+use crate::markdown::write_markdown;
+use crate::ops;
+
+/// Used when no `notes_template` is configured
+const DEFAULT_TEMPLATE: &str = "{markdown}\n## Notes\n\n";
+
+/// Fills in a notes template. Supported placeholders are `{key}` and `{markdown}`
+/// (the output of `picoref markdown`).
+fn render_template(template: &str, key: &str, data: &EntryData) -> Result<String> {
+    let mut markdown = Vec::new();
+    write_markdown(data, &mut markdown)?;
+    Ok(template
+        .replace("{key}", key)
+        .replace("{markdown}", &String::from_utf8(markdown)?))
+}
+
+/// Creates the notes file for an entry from the template (if it does not exist yet)
+pub fn create_notes(
+    root: &Path,
+    key: &str,
+    data: &EntryData,
+    template_path: Option<&Path>,
+) -> Result<()> {
+    let path = ops::notes_path(root, key);
+    if path.exists() {
+        return Ok(());
+    }
+    let template = match template_path {
+        Some(p) => std::fs::read_to_string(p)?,
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+    std::fs::write(path, render_template(&template, key, data)?)?;
+    Ok(())
+}
+
+/// Reads the notes for an entry, if it has any
+pub fn read_notes(root: &Path, key: &str) -> Result<Option<String>> {
+    let path = ops::notes_path(root, key);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let notes = std::fs::read_to_string(&path)
+        .with_context(|| format!("Could not read {}", path.to_string_lossy()))?;
+    Ok(Some(notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_notes_are_errors() {
+        let root = std::env::temp_dir().join(format!("picoref-notes-{}", std::process::id()));
+        std::fs::create_dir_all(ops::entry_root_path(&root, "key")).unwrap();
+        assert!(read_notes(&root, "key").unwrap().is_none());
+        std::fs::write(ops::notes_path(&root, "key"), b"\xff\xfe not utf-8").unwrap();
+        let err = read_notes(&root, "key").unwrap_err();
+        assert!(err.to_string().starts_with("Could not read"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,8 +1,11 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SubsecRound, Utc};
 
+use std::collections::{HashMap, HashSet};
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 
+use crate::doi::Doi;
 use crate::entry::{Entry, EntryData};
 
 pub fn entry_root_path(root: &Path, key: &str) -> PathBuf {
@@ -17,6 +20,14 @@ pub fn pdf_path(root: &Path, key: &str) -> PathBuf {
     entry_root_path(root, key).join(format!("{key}.pdf"))
 }
 
+pub fn attachment_path(root: &Path, key: &str, filename: &str) -> PathBuf {
+    entry_root_path(root, key).join(filename)
+}
+
+pub fn notes_path(root: &Path, key: &str) -> PathBuf {
+    entry_root_path(root, key).join("notes.md")
+}
+
 pub fn key_from_path(path: &Path) -> &str {
     path.file_name().unwrap().to_str().unwrap()
 }
@@ -42,12 +53,33 @@ pub fn all_keys(root: &Path) -> Result<Vec<String>> {
         .collect())
 }
 
+/// When a file was created (or last modified, if the filesystem does not record creation times)
+/// and when it was last modified
+fn file_times(path: &Path) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?;
+    let created = metadata.created().unwrap_or(modified);
+    Some((created.into(), modified.into()))
+}
+
 pub fn read_entry(path: &Path) -> Result<Entry> {
-    let content = std::fs::read_to_string(path)?;
-    Ok(toml::from_str(&content)?)
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.to_string_lossy()))?;
+    let mut entry: Entry = toml::from_str(&content)
+        .with_context(|| format!("Could not parse {}", path.to_string_lossy()))?;
+    // Entries written before timestamps were tracked
+    if entry.data.added.is_none() || entry.data.modified.is_none() {
+        if let Some((created, modified)) = file_times(path) {
+            let data = &mut entry.data;
+            data.added.get_or_insert(created.trunc_subsecs(0));
+            data.modified.get_or_insert(modified.trunc_subsecs(0));
+        }
+    }
+    Ok(entry)
 }
 
-pub fn write_entry(root: &Path, key: &str, data: &EntryData, overwrite: bool) -> Result<()> {
+/// Writes an entry's data, updating its `picoref_added` and `picoref_modified` timestamps
+pub fn write_entry(root: &Path, key: &str, data: &mut EntryData, overwrite: bool) -> Result<()> {
     let dir = entry_root_path(root, key);
     let path = data_path(root, key);
     if path.exists() && !overwrite {
@@ -57,13 +89,51 @@ pub fn write_entry(root: &Path, key: &str, data: &EntryData, overwrite: bool) ->
         );
         return Ok(());
     }
+    let now = Utc::now().trunc_subsecs(0);
+    if data.added.is_none() {
+        let created = file_times(&path).map(|(created, _)| created.trunc_subsecs(0));
+        data.added = Some(created.unwrap_or(now));
+    }
+    data.modified = Some(now);
+    let mut seen = HashSet::new();
+    data.tags.retain(|t| seen.insert(t.clone()));
     create_dir_all(dir)?;
     let content = toml::to_string_pretty(&data)?;
     std::fs::write(path, content)?;
     Ok(())
 }
 
+/// Maps the DOIs in the library (including those of preprints that were upgraded) to their entries.
+/// Entries that cannot be read are skipped (with a warning), so that one broken entry does not
+/// stop references from being added.
+pub fn doi_index(root: &Path) -> Result<HashMap<Doi, String>> {
+    let mut index = HashMap::new();
+    for key in all_keys(root)? {
+        let data = match read_entry(&data_path(root, &key)) {
+            Ok(entry) => entry.data,
+            Err(e) => {
+                eprintln!("WARNING: Skipping {key} when checking for duplicates: {e:#}");
+                continue;
+            }
+        };
+        for field in ["DOI", "preprint-DOI"] {
+            if let Some(doi) = data.fields.get(field).and_then(|d| d.as_str()) {
+                if let Ok(doi) = Doi::parse(doi) {
+                    index.insert(doi, key.clone());
+                }
+            }
+        }
+    }
+    Ok(index)
+}
+
 pub fn update_metadata(data: &mut EntryData, key: &str) -> Result<()> {
+    // Store DOIs in the same form regardless of where they came from
+    if let Some(doi) = data.fields.get("DOI").and_then(|d| d.as_str()) {
+        let doi = Doi::parse(doi)?;
+        data.fields
+            .insert("DOI".to_string(), doi.to_string().into());
+    }
     let old_id = data.fields.insert("id".to_string(), key.into());
     if let Some(i) = old_id {
         data.fields.insert("legacy-id".to_string(), i);
@@ -74,3 +144,19 @@ pub fn update_metadata(data: &mut EntryData, key: &str) -> Result<()> {
     }
     Ok(())
 }
+
+/// Renames an entry's directory and the files named after its key
+pub fn move_entry(root: &Path, old_key: &str, new_key: &str) -> Result<()> {
+    let new_dir = entry_root_path(root, new_key);
+    if new_dir.exists() {
+        anyhow::bail!("An entry already exists at: {}", new_dir.to_string_lossy());
+    }
+    std::fs::rename(entry_root_path(root, old_key), &new_dir)?;
+    for ext in ["toml", "pdf"] {
+        let old_path = new_dir.join(format!("{old_key}.{ext}"));
+        if old_path.exists() {
+            std::fs::rename(old_path, new_dir.join(format!("{new_key}.{ext}")))?;
+        }
+    }
+    Ok(())
+}
@@ -1,33 +1,127 @@
 use std::fs::File;
+use std::io::{Read, Write};
 use std::path::Path;
 use std::time::Duration;
 
 use anyhow::Result;
-use indicatif::ProgressBar;
+use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::entry::Source;
+use crate::config::Config;
+use crate::entry::{EntryData, Source};
 use crate::fetch;
+use crate::http;
 
-pub fn download_pdf(source: &Source, email: &str, title: Option<&str>, key: &str, dest: &Path) -> Result<()> {
-    let mut pb = ProgressBar::new_spinner().with_message("Searching for PDF");
+/// PDF files must contain this signature within their first 1024 bytes
+const PDF_MAGIC: &[u8] = b"%PDF-";
+const SNIFF_LEN: usize = 1024;
+
+/// Finds the publisher's link to the full text PDF in CrossRef's metadata,
+/// falling back to the DOI (which usually resolves to the publisher's landing page).
+fn publisher_pdf_url(data: &EntryData, doi: &str) -> String {
+    data.fields
+        .get("link")
+        .and_then(|links| links.as_array())
+        .and_then(|links| {
+            links.iter().find_map(|link| {
+                if link.get("content-type")?.as_str()? == "application/pdf" {
+                    Some(link.get("URL")?.as_str()?.to_string())
+                } else {
+                    None
+                }
+            })
+        })
+        .unwrap_or_else(|| format!("https://doi.org/{doi}"))
+}
+
+pub fn download_pdf(
+    source: &Source,
+    conf: &Config,
+    data: &EntryData,
+    key: &str,
+    dest: &Path,
+) -> Result<()> {
+    let pb = ProgressBar::new_spinner().with_message("Searching for PDF");
     pb.enable_steady_tick(Duration::from_millis(100));
-    let pdf_url = fetch::fetch_pdf_url(source, email, title)?;
+    let pdf_url = match (
+        fetch::fetch_pdf_url(source, conf, data),
+        &conf.network.ezproxy_prefix,
+        source,
+    ) {
+        (Ok(url), _, _) => url,
+        (Err(e), Some(prefix), Source::Other(doi)) => {
+            pb.println(format!("{e}, trying the publisher's PDF through EZproxy"));
+            format!("{prefix}{}", publisher_pdf_url(data, doi))
+        }
+        (Err(e), _, _) => {
+            pb.finish_and_clear();
+            return Err(e);
+        }
+    };
     pb.finish_with_message("PDF found");
-    let pdf_response = ureq::get(&pdf_url).call()?;
-    let content_type = pdf_response.content_type().to_owned();
-    if content_type != "application/pdf" {
-        eprintln!("Found a URL but it is not a direct PDF link:");
-        eprintln!("  {pdf_url}");
-        eprintln!();
-        eprintln!("Download the PDF manually, then run:");
-        eprintln!("  picoref pdf {key} --file <path-to-pdf>");
-        anyhow::bail!("Could not automatically download PDF");
+
+    // Download to a temporary file next to the destination so that a failed download
+    // never leaves a truncated PDF in the library.
+    let part_path = dest.with_extension("pdf.part");
+    let result = download_to(&pdf_url, conf.max_pdf_size, &part_path);
+    match result {
+        Ok(true) => Ok(std::fs::rename(&part_path, dest)?),
+        Ok(false) => {
+            let _ = std::fs::remove_file(&part_path);
+            eprintln!("Found a URL but it is not a direct PDF link:");
+            eprintln!("  {pdf_url}");
+            eprintln!();
+            eprintln!("Download the PDF manually, then run:");
+            eprintln!("  picoref pdf {key} --file <path-to-pdf>");
+            anyhow::bail!("Could not automatically download PDF");
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&part_path);
+            Err(e)
+        }
+    }
+}
+
+/// Streams the PDF at `url` to `dest`. Returns `false` (without writing anything) if the
+/// response is not a PDF, regardless of the `Content-Type` the server claims.
+fn download_to(url: &str, max_size: u64, dest: &Path) -> Result<bool> {
+    let response = http::get(url).call()?;
+    let content_length = response
+        .header("Content-Length")
+        .and_then(|l| l.parse::<u64>().ok());
+    if matches!(content_length, Some(len) if len > max_size) {
+        anyhow::bail!("PDF is larger than the maximum size ({max_size} bytes)");
     }
-    let mut pdf_data = pdf_response.into_reader();
-    pb = ProgressBar::new_spinner().with_message("Downloading");
+    // Read one byte past the limit so that oversized responses can be detected
+    let mut reader = response.into_reader().take(max_size + 1);
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    (&mut reader)
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    if !head.windows(PDF_MAGIC.len()).any(|w| w == PDF_MAGIC) {
+        return Ok(false);
+    }
+
+    let pb = match content_length {
+        Some(len) => ProgressBar::new(len).with_style(ProgressStyle::with_template(
+            "{msg} [{wide_bar}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+        )?),
+        None => ProgressBar::new_spinner().with_style(ProgressStyle::with_template(
+            "{spinner} {msg} {bytes} ({bytes_per_sec})",
+        )?),
+    }
+    .with_message("Downloading");
     pb.enable_steady_tick(Duration::from_millis(100));
-    let mut new_file = File::create(dest)?;
-    std::io::copy(&mut pdf_data, &mut new_file)?;
+
+    let mut file = File::create(dest)?;
+    file.write_all(&head)?;
+    pb.inc(head.len() as u64);
+    let written = head.len() as u64 + std::io::copy(&mut pb.wrap_read(reader), &mut file)?;
+    if written > max_size {
+        pb.abandon_with_message("Download aborted");
+        anyhow::bail!("PDF is larger than the maximum size ({max_size} bytes)");
+    }
+    file.sync_all()?;
     pb.finish_with_message("Download complete");
-    Ok(())
+    Ok(true)
 }
@@ -0,0 +1,557 @@
+//! An interactive picker for entries (`picoref pick`), with fuzzy search, a preview of the
+//! selected entry, and actions that can be applied to it.
+
+use std::collections::BTreeSet;
+use std::io::{stderr, IsTerminal, Stderr, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+use crate::list::Item;
+use crate::markdown::write_markdown;
+use crate::tags::{self, Vocabulary};
+use crate::{editor, ops};
+
+const HELP: &str =
+    "enter: done  tab: select  ctrl-o: open PDF  ctrl-y: copy key  ctrl-r: copy citation  ctrl-t: add tag  esc: cancel";
+
+/// Scores how well `query` matches `text` (higher is better), or `None` if it does not match.
+/// Each word of the query must appear in the text, but its letters do not have to be adjacent.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    for word in query.to_lowercase().split_whitespace() {
+        let mut position = 0;
+        let mut previous: Option<usize> = None;
+        for c in word.chars() {
+            let found = position + text[position..].iter().position(|t| *t == c)?;
+            score += 1;
+            if previous.is_some_and(|p| p + 1 == found) {
+                score += 5;
+            }
+            if found == 0 || !text[found - 1].is_alphanumeric() {
+                score += 3;
+            }
+            previous = Some(found);
+            position = found + 1;
+        }
+    }
+    Some(score)
+}
+
+/// A plain text citation, e.g. "A & B (2020). Title. Journal."
+fn citation(item: &Item) -> String {
+    let author = item.field("author");
+    let year = item.field("year");
+    let mut parts = Vec::new();
+    match (author.is_empty(), year.is_empty()) {
+        (false, false) => parts.push(format!("{author} ({year})")),
+        (false, true) => parts.push(author),
+        (true, false) => parts.push(format!("({year})")),
+        (true, true) => {}
+    }
+    parts.extend(
+        ["title", "container"]
+            .map(|f| item.field(f))
+            .into_iter()
+            .filter(|v| !v.is_empty()),
+    );
+    let mut citation = parts
+        .iter()
+        .map(|p| p.trim_end_matches('.'))
+        .collect::<Vec<_>>()
+        .join(". ");
+    citation.push('.');
+    let doi = item.field("doi");
+    if !doi.is_empty() {
+        citation.push_str(&format!(" https://doi.org/{doi}"));
+    }
+    citation
+}
+
+/// Copies text using the first clipboard tool that is installed, falling back to asking the
+/// terminal to do it (OSC 52, which many terminals support)
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    const TOOLS: [&[&str]; 5] = [
+        &["pbcopy"],
+        &["wl-copy"],
+        &["xclip", "-selection", "clipboard"],
+        &["xsel", "--clipboard", "--input"],
+        &["clip.exe"],
+    ];
+    for tool in TOOLS {
+        let Ok(mut child) = Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        child
+            .stdin
+            .take()
+            .context("Could not write to the clipboard")?
+            .write_all(text.as_bytes())?;
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    let mut stderr = stderr();
+    write!(stderr, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stderr.flush()?;
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (u32::from(*b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+enum Mode {
+    Search,
+    /// Typing the name of a tag to add
+    Tag {
+        tag: String,
+        /// Whether the user was warned that the tag is not in the vocabulary
+        warned: bool,
+    },
+}
+
+struct Picker<'a> {
+    root: &'a Path,
+    vocabulary: Option<&'a Vocabulary>,
+    items: Vec<Item>,
+    query: String,
+    /// Indices into `items` of the entries matching the query, best match first
+    matches: Vec<usize>,
+    list_state: ListState,
+    /// Indices into `items` of the entries that were selected with tab
+    selected: BTreeSet<usize>,
+    mode: Mode,
+    /// Feedback from the last action
+    message: Option<String>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(root: &'a Path, vocabulary: Option<&'a Vocabulary>, items: Vec<Item>) -> Self {
+        let mut picker = Self {
+            root,
+            vocabulary,
+            items,
+            query: String::new(),
+            matches: Vec::new(),
+            list_state: ListState::default(),
+            selected: BTreeSet::new(),
+            mode: Mode::Search,
+            message: None,
+        };
+        picker.update_matches();
+        picker
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                let text = ["key", "year", "author", "title"].map(|f| item.field(f));
+                Some((fuzzy_score(&self.query, &text.join(" "))?, i))
+            })
+            .collect();
+        // Stable, so entries that match equally well keep their order
+        scored.sort_by_key(|(score, _)| -score);
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.list_state
+            .select((!self.matches.is_empty()).then_some(0));
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.matches.get(self.list_state.selected()?).copied()
+    }
+
+    /// The entries that actions apply to: those selected with tab, or else the current one
+    fn targets(&self) -> Vec<usize> {
+        if self.selected.is_empty() {
+            self.current().into_iter().collect()
+        } else {
+            self.selected.iter().copied().collect()
+        }
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        let last = self.matches.len() as isize - 1;
+        self.list_state
+            .select(Some((current + offset).clamp(0, last) as usize));
+    }
+
+    fn open_pdf(&self) -> Result<String> {
+        let i = self.current().context("No entry selected")?;
+        let key = &self.items[i].key;
+        let path = ops::pdf_path(self.root, key);
+        if !path.exists() {
+            anyhow::bail!("{key} has no PDF");
+        }
+        editor::open_with_system(&path)?;
+        Ok(format!("Opened {}", path.to_string_lossy()))
+    }
+
+    fn add_tag(&mut self, tag: &str) -> Result<String> {
+        let targets = self.targets();
+        for &i in targets.iter() {
+            let item = &mut self.items[i];
+            item.entry.data.tags.push(tag.to_string());
+            ops::write_entry(self.root, &item.key, &mut item.entry.data, true)?;
+        }
+        Ok(format!("Added `{tag}` to {} entries", targets.len()))
+    }
+
+    /// Handles a key press, returning the keys of the picked entries once the user is done
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Result<Vec<String>>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if let Mode::Tag { tag, warned } = &mut self.mode {
+            match key.code {
+                KeyCode::Enter => {
+                    // Checked before anything is written, as `tags add` does
+                    let tag = match tags::parse_tag(tag.trim()) {
+                        Ok(tag) if tag.is_empty() => Err(anyhow::anyhow!("No tag entered")),
+                        parsed => parsed,
+                    };
+                    let tag = match tag {
+                        Ok(tag) => tag,
+                        Err(e) => {
+                            self.message = Some(format!("{e:#}"));
+                            return None;
+                        }
+                    };
+                    if !*warned {
+                        if let Some(problem) = self.vocabulary.and_then(|v| v.check(&tag)) {
+                            *warned = true;
+                            self.message =
+                                Some(format!("{problem} (press enter again to add it anyway)"));
+                            return None;
+                        }
+                    }
+                    self.mode = Mode::Search;
+                    self.message = Some(self.add_tag(&tag).unwrap_or_else(|e| format!("{e:#}")));
+                }
+                KeyCode::Esc => self.mode = Mode::Search,
+                KeyCode::Char('c') if ctrl => self.mode = Mode::Search,
+                KeyCode::Backspace => {
+                    tag.pop();
+                    *warned = false;
+                }
+                KeyCode::Char(c) => {
+                    tag.push(c);
+                    *warned = false;
+                }
+                _ => {}
+            }
+            return None;
+        }
+
+        let result = match key.code {
+            KeyCode::Esc => return Some(Err(anyhow::anyhow!("Cancelled"))),
+            KeyCode::Char('c') if ctrl => return Some(Err(anyhow::anyhow!("Cancelled"))),
+            KeyCode::Enter => {
+                let targets = self.targets();
+                return Some(Ok(targets
+                    .into_iter()
+                    .map(|i| self.items[i].key.clone())
+                    .collect()));
+            }
+            KeyCode::Up => {
+                self.move_by(-1);
+                Ok(())
+            }
+            KeyCode::Char('p') if ctrl => {
+                self.move_by(-1);
+                Ok(())
+            }
+            KeyCode::Down => {
+                self.move_by(1);
+                Ok(())
+            }
+            KeyCode::Char('n') if ctrl => {
+                self.move_by(1);
+                Ok(())
+            }
+            KeyCode::PageUp => {
+                self.move_by(-10);
+                Ok(())
+            }
+            KeyCode::PageDown => {
+                self.move_by(10);
+                Ok(())
+            }
+            KeyCode::Tab => {
+                if let Some(i) = self.current() {
+                    if !self.selected.remove(&i) {
+                        self.selected.insert(i);
+                    }
+                    self.move_by(1);
+                }
+                Ok(())
+            }
+            KeyCode::Char('o') if ctrl => self.open_pdf().map(|m| self.message = Some(m)),
+            KeyCode::Char('y') if ctrl => {
+                let keys: Vec<&str> = self
+                    .targets()
+                    .into_iter()
+                    .map(|i| self.items[i].key.as_str())
+                    .collect();
+                copy_to_clipboard(&keys.join(" "))
+                    .map(|_| self.message = Some(format!("Copied {}", keys.join(" "))))
+            }
+            KeyCode::Char('r') if ctrl => {
+                let citations: Vec<String> = self
+                    .targets()
+                    .into_iter()
+                    .map(|i| citation(&self.items[i]))
+                    .collect();
+                copy_to_clipboard(&citations.join("\n"))
+                    .map(|_| self.message = Some(format!("Copied {} citation(s)", citations.len())))
+            }
+            KeyCode::Char('t') if ctrl => {
+                self.mode = Mode::Tag {
+                    tag: String::new(),
+                    warned: false,
+                };
+                Ok(())
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.update_matches();
+                Ok(())
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+                Ok(())
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.update_matches();
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            self.message = Some(format!("{e:#}"));
+        }
+        None
+    }
+
+    fn preview(&self) -> String {
+        let Some(i) = self.current() else {
+            return String::new();
+        };
+        let item = &self.items[i];
+        let mut markdown = Vec::new();
+        if let Err(e) = write_markdown(&item.entry.data, &mut markdown) {
+            return format!("{e:#}");
+        }
+        let mut preview = String::from_utf8_lossy(&markdown).into_owned();
+        if ops::pdf_path(self.root, &item.key).exists() {
+            preview.push_str("PDF: yes\n");
+        }
+        preview
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [input_area, main_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main_area);
+
+        let (prompt, input) = match &self.mode {
+            Mode::Search => ("> ", self.query.as_str()),
+            Mode::Tag { tag, .. } => ("Add tag: ", tag.as_str()),
+        };
+        let input_line = Line::from(vec![
+            Span::styled(prompt, Style::new().bold()),
+            Span::raw(input),
+            Span::styled(
+                format!("  {}/{}", self.matches.len(), self.items.len()),
+                Style::new().dim(),
+            ),
+        ]);
+        frame.render_widget(Paragraph::new(input_line), input_area);
+        frame.set_cursor_position((
+            input_area.x + (prompt.chars().count() + input.chars().count()) as u16,
+            input_area.y,
+        ));
+
+        let rows: Vec<ListItem> = self
+            .matches
+            .iter()
+            .map(|&i| {
+                let item = &self.items[i];
+                let marker = if self.selected.contains(&i) {
+                    "● "
+                } else {
+                    "  "
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(marker),
+                    Span::styled(item.key.clone(), Style::new().bold()),
+                    Span::raw("  "),
+                    Span::raw(item.field("title")),
+                ]))
+            })
+            .collect();
+        let list = List::new(rows)
+            .block(Block::bordered())
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let preview = Paragraph::new(self.preview())
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered());
+        frame.render_widget(preview, preview_area);
+
+        let status = match &self.message {
+            Some(message) => Span::raw(message.clone()),
+            None => Span::styled(HELP, Style::new().dim()),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stderr>>) -> Result<Vec<String>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                self.message = None;
+                if let Some(result) = self.handle_key(key) {
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+/// Lets the user pick entries interactively, returning their keys.
+/// The interface is drawn on stderr so that the keys can be piped to another command.
+pub fn pick(root: &Path, vocabulary: Option<&Vocabulary>, items: Vec<Item>) -> Result<Vec<String>> {
+    if !stderr().is_terminal() {
+        anyhow::bail!("`picoref pick` needs to be run in a terminal");
+    }
+    let mut picker = Picker::new(root, vocabulary, items);
+    let guard = TerminalGuard::enter()?;
+    let result = Terminal::new(CrosstermBackend::new(stderr()))
+        .map_err(anyhow::Error::from)
+        .and_then(|mut terminal| picker.run(&mut terminal));
+    guard.leave()?;
+    result
+}
+
+/// Puts the terminal in raw mode on the alternate screen, and restores it when dropped so that
+/// it is restored even if picking fails or panics
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        // The panic message would be lost on the alternate screen, so restore the terminal first
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = restore_terminal();
+            hook(info);
+        }));
+        enable_raw_mode()?;
+        let guard = Self;
+        execute!(stderr(), EnterAlternateScreen)?;
+        Ok(guard)
+    }
+
+    /// Restores the terminal, reporting any errors (which dropping the guard ignores)
+    fn leave(self) -> Result<()> {
+        std::mem::forget(self);
+        restore_terminal()
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore_terminal();
+    }
+}
+
+fn restore_terminal() -> Result<()> {
+    disable_raw_mode()?;
+    execute!(stderr(), LeaveAlternateScreen)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches() {
+        let text = "vaswani_2017_attention-all 2017 Ashish Vaswani Attention Is All You Need";
+        assert!(fuzzy_score("attn need", text).is_some());
+        assert!(fuzzy_score("", text).is_some());
+        assert!(fuzzy_score("transformer", text).is_none());
+        assert!(fuzzy_score("vaswani", text) > fuzzy_score("vsw", text));
+    }
+
+    #[test]
+    fn rejects_collection_tags() {
+        let mut picker = Picker::new(Path::new("/nonexistent"), None, Vec::new());
+        picker.mode = Mode::Tag {
+            tag: "@thesis".to_string(),
+            warned: false,
+        };
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(picker.handle_key(key).is_none());
+        assert!(matches!(picker.mode, Mode::Tag { .. }));
+        assert!(picker.message.unwrap().starts_with("Tags cannot start with @"));
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b"key"), "a2V5");
+        assert_eq!(base64(b"ke"), "a2U=");
+        assert_eq!(base64(b"k"), "aw==");
+    }
+}
@@ -0,0 +1,260 @@
+//! Queries used to define collections (and `--query`), e.g. `tag:thesis -tag:excluded year:2015..`
+//!
+//! A query is a list of terms that must all match. A term is negated with a leading `-`, and can be:
+//! - `tag:NAME` (the tag or a tag beneath it)
+//! - `year:2015`, `year:2015..`, `year:..2020`, or `year:2015..2020`
+//! - `added:2026-09-01..` (and the other forms of range)
+//! - `type:CSL-TYPE`
+//! - `status:unread|reading|read|skimmed`
+//! - `rating:4..` (and the other forms of range)
+//! - `has:notes|pdf|doi|abstract`
+//! - anything else, which is searched for in the key, title, and authors (ignoring case)
+//!
+//! Values containing spaces can be quoted, e.g. `tag:"machine learning"`.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use clap::ValueEnum;
+
+use crate::entry::{Entry, ReadingStatus};
+use crate::names::parse_names;
+use crate::{notes, ops, tags};
+
+/// An inclusive range, which may be open at either end
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Range<T> {
+    start: Option<T>,
+    end: Option<T>,
+}
+
+impl<T: FromStr + PartialOrd + Clone> Range<T> {
+    fn parse(s: &str) -> Result<Self>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let parse = |s: &str| -> Result<Option<T>> {
+            Ok(if s.is_empty() { None } else { Some(s.parse()?) })
+        };
+        match s.split_once("..") {
+            Some((start, end)) => Ok(Self {
+                start: parse(start)?,
+                end: parse(end)?,
+            }),
+            None => {
+                let value = parse(s)?.context("Missing value")?;
+                Ok(Self {
+                    start: Some(value.clone()),
+                    end: Some(value),
+                })
+            }
+        }
+    }
+
+    fn contains(&self, value: &T) -> bool {
+        self.start.as_ref().is_none_or(|s| value >= s)
+            && self.end.as_ref().is_none_or(|e| value <= e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Has {
+    Notes,
+    Pdf,
+    Doi,
+    Abstract,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    Tag(String),
+    Year(Range<u64>),
+    Added(Range<NaiveDate>),
+    Type(String),
+    Status(ReadingStatus),
+    Rating(Range<u8>),
+    Has(Has),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    negated: bool,
+    condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query(Vec<Term>);
+
+/// Splits a query into terms on whitespace, except inside double quotes (which are removed)
+fn split_terms(query: &str) -> Result<Vec<String>> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("Unclosed quote in query: {query}");
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    Ok(terms)
+}
+
+impl Condition {
+    fn parse(term: &str) -> Result<Self> {
+        let Some((field, value)) = term.split_once(':') else {
+            return Ok(Self::Text(term.to_lowercase()));
+        };
+        Ok(match field {
+            "tag" => Self::Tag(value.to_string()),
+            "year" => Self::Year(Range::parse(value)?),
+            "added" => Self::Added(Range::parse(value)?),
+            "type" => Self::Type(value.to_string()),
+            "status" => {
+                Self::Status(ReadingStatus::from_str(value, true).map_err(|e| anyhow::anyhow!(e))?)
+            }
+            "rating" => Self::Rating(Range::parse(value)?),
+            "has" => Self::Has(match value {
+                "notes" => Has::Notes,
+                "pdf" => Has::Pdf,
+                "doi" => Has::Doi,
+                "abstract" => Has::Abstract,
+                _ => anyhow::bail!("expected has:notes, has:pdf, has:doi, or has:abstract"),
+            }),
+            // e.g. a title containing a colon
+            _ => Self::Text(term.to_lowercase()),
+        })
+    }
+
+    fn matches(&self, root: &Path, key: &str, entry: &Entry) -> Result<bool> {
+        let data = &entry.data;
+        let has_field = |field: &str| {
+            data.fields
+                .get(field)
+                .and_then(|v| v.as_str())
+                .is_some_and(|s| !s.trim().is_empty())
+        };
+        Ok(match self {
+            Self::Tag(tag) => data.tags.iter().any(|t| tags::is_within(t, tag)),
+            Self::Year(range) => data.year().is_some_and(|y| range.contains(&y)),
+            Self::Added(range) => data
+                .added
+                .is_some_and(|a| range.contains(&a.with_timezone(&Local).date_naive())),
+            Self::Type(t) => data.fields.get("type").and_then(|v| v.as_str()) == Some(t),
+            Self::Status(status) => data.status == Some(*status),
+            Self::Rating(range) => data.rating.is_some_and(|r| range.contains(&r)),
+            Self::Has(Has::Notes) => notes::read_notes(root, key)?.is_some(),
+            Self::Has(Has::Pdf) => ops::pdf_path(root, key).exists(),
+            Self::Has(Has::Doi) => has_field("DOI"),
+            Self::Has(Has::Abstract) => has_field("abstract"),
+            Self::Text(text) => {
+                let title = data.fields.get("title").and_then(|v| v.as_str());
+                let authors = data.fields.get("author").map(parse_names);
+                key.to_lowercase().contains(text)
+                    || title.is_some_and(|t| t.to_lowercase().contains(text))
+                    || authors.is_some_and(|names| {
+                        names
+                            .iter()
+                            .any(|n| n.display().to_lowercase().contains(text))
+                    })
+            }
+        })
+    }
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self> {
+        split_terms(query)?
+            .into_iter()
+            .map(|term| {
+                let (negated, condition) = match term.strip_prefix('-') {
+                    Some(rest) if !rest.is_empty() => (true, rest),
+                    _ => (false, term.as_str()),
+                };
+                let condition = Condition::parse(condition)
+                    .with_context(|| format!("Invalid query term: {term}"))?;
+                Ok(Term { negated, condition })
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    pub fn matches(&self, root: &Path, key: &str, entry: &Entry) -> Result<bool> {
+        for term in self.0.iter() {
+            if term.condition.matches(root, key, entry)? == term.negated {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::entry::EntryData;
+
+    fn entry(tags: &[&str], year: u64) -> Entry {
+        Entry {
+            source: None,
+            data: EntryData {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                fields: serde_json::from_value(json!({
+                    "type": "article-journal",
+                    "title": "Attention Is All You Need",
+                    "issued": {"date-parts": [[year]]},
+                }))
+                .unwrap(),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn matches(query: &str, entry: &Entry) -> bool {
+        let root = Path::new("/nonexistent");
+        Query::parse(query)
+            .unwrap()
+            .matches(root, "key", entry)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_terms() {
+        assert_eq!(
+            split_terms(r#"tag:"machine learning" -year:..2015"#).unwrap(),
+            ["tag:machine learning", "-year:..2015"]
+        );
+        assert!(Query::parse("year:abc").is_err());
+        assert!(Query::parse("status:done").is_err());
+        assert!(Query::parse("tag:\"unclosed").is_err());
+    }
+
+    #[test]
+    fn matches_entries() {
+        let thesis = entry(&["thesis/ch1"], 2017);
+        let excluded = entry(&["thesis", "excluded"], 2017);
+        let query = "tag:thesis -tag:excluded year:2015..";
+        assert!(matches(query, &thesis));
+        assert!(!matches(query, &excluded));
+        assert!(!matches(query, &entry(&["thesis"], 2014)));
+        assert!(matches("year:2017", &thesis));
+        assert!(!matches("year:2016", &thesis));
+        assert!(matches("attention type:article-journal", &thesis));
+        assert!(matches("-has:pdf -status:read", &thesis));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use chrono::Local;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::entry::Entry;
+use crate::names::parse_names;
+use crate::ops;
+
+/// The number of entries that share a value (e.g. a year or a tag)
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Count {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Summary of (part of) a library, as reported by `picoref stats`
+#[derive(Serialize, Debug)]
+pub struct Stats {
+    pub entries: usize,
+    pub with_pdf: usize,
+    pub without_pdf: usize,
+    /// Keys of the entries without an abstract
+    pub missing_abstract: Vec<String>,
+    /// Keys of the entries without a DOI
+    pub missing_doi: Vec<String>,
+    pub by_year: Vec<Count>,
+    pub by_type: Vec<Count>,
+    pub by_container: Vec<Count>,
+    pub by_tag: Vec<Count>,
+    pub by_source: Vec<Count>,
+    pub top_authors: Vec<Count>,
+    pub by_status: Vec<Count>,
+    pub by_rating: Vec<Count>,
+    /// Entries added to the library per month (e.g. "2026-09")
+    pub added_by_month: Vec<Count>,
+    /// Keys of the most recently added entries, newest first
+    pub recently_added: Vec<String>,
+}
+
+#[derive(Default)]
+struct Counter(HashMap<String, usize>);
+
+impl Counter {
+    fn add(&mut self, name: impl Into<String>) {
+        *self.0.entry(name.into()).or_default() += 1;
+    }
+
+    /// The counts from most to least common (ties are ordered by name), keeping at most `limit`
+    fn most_common(self, limit: Option<usize>) -> Vec<Count> {
+        let mut counts: Vec<Count> = self
+            .0
+            .into_iter()
+            .map(|(name, count)| Count { name, count })
+            .collect();
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+        counts.truncate(limit.unwrap_or(counts.len()));
+        counts
+    }
+
+    fn by_name(self) -> Vec<Count> {
+        let mut counts: Vec<Count> = self
+            .0
+            .into_iter()
+            .map(|(name, count)| Count { name, count })
+            .collect();
+        counts.sort_by(|a, b| a.name.cmp(&b.name));
+        counts
+    }
+}
+
+/// Computes statistics for the given entries.
+/// Only the `top` most common container titles and authors (and most recent entries) are kept.
+pub fn compute(root: &Path, entries: &[(String, Entry)], top: usize) -> Stats {
+    let mut with_pdf = 0;
+    let mut missing_abstract = Vec::new();
+    let mut missing_doi = Vec::new();
+    let mut years = Counter::default();
+    let mut types = Counter::default();
+    let mut containers = Counter::default();
+    let mut tags = Counter::default();
+    let mut sources = Counter::default();
+    let mut authors = Counter::default();
+    let mut months = Counter::default();
+    let mut statuses = Counter::default();
+    let mut ratings = Counter::default();
+
+    for (key, entry) in entries {
+        let fields = &entry.data.fields;
+        let get_str = |field: &str| {
+            fields
+                .get(field)
+                .and_then(|v| v.as_str())
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+        };
+        if ops::pdf_path(root, key).exists() {
+            with_pdf += 1;
+        }
+        if get_str("abstract").is_none() {
+            missing_abstract.push(key.clone());
+        }
+        if get_str("DOI").is_none() {
+            missing_doi.push(key.clone());
+        }
+        years.add(
+            entry
+                .data
+                .year()
+                .map_or("unknown".to_string(), |y| y.to_string()),
+        );
+        types.add(get_str("type").unwrap_or("unknown"));
+        if let Some(container) = get_str("container-title") {
+            containers.add(container);
+        }
+        for tag in entry.data.tags.iter() {
+            tags.add(tag.as_str());
+        }
+        sources.add(entry.source.as_ref().map_or("no DOI", |s| s.name()));
+        if let Some(status) = entry.data.status {
+            statuses.add(status.to_string());
+        }
+        if let Some(rating) = entry.data.rating {
+            ratings.add(rating.to_string());
+        }
+        if let Some(added) = entry.data.added {
+            months.add(added.with_timezone(&Local).format("%Y-%m").to_string());
+        }
+        if let Some(names) = fields.get("author") {
+            for name in parse_names(names) {
+                authors.add(name.display());
+            }
+        }
+    }
+    missing_abstract.sort();
+    missing_doi.sort();
+    let recently_added = entries
+        .iter()
+        .filter_map(|(key, entry)| Some((entry.data.added?, key)))
+        .sorted_by(|a, b| b.cmp(a))
+        .take(top)
+        .map(|(_, key)| key.clone())
+        .collect();
+
+    Stats {
+        entries: entries.len(),
+        with_pdf,
+        without_pdf: entries.len() - with_pdf,
+        missing_abstract,
+        missing_doi,
+        by_year: years.by_name(),
+        by_type: types.most_common(None),
+        by_container: containers.most_common(Some(top)),
+        by_tag: tags.most_common(None),
+        by_source: sources.most_common(None),
+        top_authors: authors.most_common(Some(top)),
+        by_status: statuses.most_common(None),
+        by_rating: ratings.by_name(),
+        added_by_month: months.by_name(),
+        recently_added,
+    }
+}
+
+fn write_counts(f: &mut fmt::Formatter<'_>, heading: &str, counts: &[Count]) -> fmt::Result {
+    if counts.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "\n{heading}")?;
+    let width = counts
+        .iter()
+        .map(|c| c.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for Count { name, count } in counts {
+        writeln!(f, "  {name:<width$}  {count}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Entries: {}", self.entries)?;
+        writeln!(
+            f,
+            "With PDF: {} ({} without)",
+            self.with_pdf, self.without_pdf
+        )?;
+        writeln!(f, "Missing abstract: {}", self.missing_abstract.len())?;
+        writeln!(f, "Missing DOI: {}", self.missing_doi.len())?;
+        write_counts(f, "Year", &self.by_year)?;
+        write_counts(f, "Type", &self.by_type)?;
+        write_counts(f, "Source", &self.by_source)?;
+        write_counts(f, "Container title", &self.by_container)?;
+        write_counts(f, "Tag", &self.by_tag)?;
+        write_counts(f, "Top authors", &self.top_authors)?;
+        write_counts(f, "Reading status", &self.by_status)?;
+        write_counts(f, "Rating", &self.by_rating)?;
+        write_counts(f, "Added", &self.added_by_month)?;
+        if !self.recently_added.is_empty() {
+            writeln!(f, "\nRecently added")?;
+            for key in self.recently_added.iter() {
+                writeln!(f, "  {key}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_common_first() {
+        let mut counter = Counter::default();
+        for name in ["b", "a", "c", "c", "b", "c"] {
+            counter.add(name);
+        }
+        let names = |counts: Vec<Count>| counts.into_iter().map(|c| c.name).collect::<Vec<_>>();
+        assert_eq!(
+            names(Counter(counter.0.clone()).most_common(None)),
+            ["c", "b", "a"]
+        );
+        assert_eq!(
+            names(Counter(counter.0.clone()).most_common(Some(2))),
+            ["c", "b"]
+        );
+        assert_eq!(names(counter.by_name()), ["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Tags can form a hierarchy by separating their parts with slashes
+//! (e.g. `project/grant-2025/aim1` is beneath `project/grant-2025` and `project`).
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+
+use crate::ops;
+
+pub const SEPARATOR: char = '/';
+
+/// Parses a tag given on the command line. Tags cannot start with "@", which marks a collection.
+pub fn parse_tag(tag: &str) -> Result<String> {
+    if tag.starts_with('@') {
+        anyhow::bail!("Tags cannot start with @, which marks a collection");
+    }
+    Ok(tag.to_string())
+}
+
+/// Whether `tag` is `ancestor` or beneath it
+pub fn is_within(tag: &str, ancestor: &str) -> bool {
+    let ancestor = ancestor.trim_end_matches(SEPARATOR);
+    match tag.strip_prefix(ancestor) {
+        Some(rest) => rest.is_empty() || rest.starts_with(SEPARATOR),
+        None => false,
+    }
+}
+
+/// Renames `tag` if it is `old` or beneath it (e.g. `a/b/c` becomes `x/c` when `a/b` is renamed
+/// to `x`), returning `None` if it is unaffected
+pub fn rename(tag: &str, old: &str, new: &str) -> Option<String> {
+    if !is_within(tag, old) {
+        return None;
+    }
+    let rest = &tag[old.trim_end_matches(SEPARATOR).len()..];
+    Some(format!("{}{rest}", new.trim_end_matches(SEPARATOR)))
+}
+
+/// Renames the `old` tags (and the tags beneath them) to `new` in every entry in the library,
+/// returning the keys of the entries that were changed
+pub fn rename_all(root: &Path, old: &[String], new: &str) -> Result<Vec<String>> {
+    let mut changed = Vec::new();
+    for key in ops::all_keys(root)? {
+        let mut entry = ops::read_entry(&ops::data_path(root, &key))?;
+        let mut renamed = false;
+        for tag in entry.data.tags.iter_mut() {
+            if let Some(new_tag) = old.iter().find_map(|o| rename(tag, o, new)) {
+                renamed |= new_tag != *tag;
+                *tag = new_tag;
+            }
+        }
+        if renamed {
+            ops::write_entry(root, &key, &mut entry.data, true)?;
+            changed.push(key);
+        }
+    }
+    changed.sort();
+    Ok(changed)
+}
+
+/// The tag and each of its ancestors, e.g. "a", "a/b", and "a/b/c" for "a/b/c"
+fn with_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices(SEPARATOR)
+        .map(|(i, _)| &tag[..i])
+        .chain([tag])
+        .filter(|t| !t.is_empty() && !t.ends_with(SEPARATOR))
+}
+
+/// A line of `tags list --tree`
+#[derive(Debug, PartialEq, Eq)]
+pub struct TreeNode<'a> {
+    pub depth: usize,
+    /// The last part of the tag
+    pub name: &'a str,
+    /// The number of entries with this tag or a tag beneath it
+    pub count: usize,
+}
+
+/// Arranges the tags of each entry into a tree, in depth-first order
+pub fn tree<'a>(entry_tags: impl IntoIterator<Item = &'a [String]>) -> Vec<TreeNode<'a>> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tags in entry_tags {
+        // Entries with several tags beneath the same ancestor only count once for it
+        let tags: HashSet<&str> = tags.iter().flat_map(|t| with_ancestors(t)).collect();
+        for tag in tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    let mut tags: Vec<(Vec<&str>, usize)> = counts
+        .into_iter()
+        .map(|(tag, count)| (tag.split(SEPARATOR).collect(), count))
+        .collect();
+    tags.sort();
+    tags.into_iter()
+        .map(|(parts, count)| TreeNode {
+            depth: parts.len() - 1,
+            name: parts.last().expect("split to return at least one part"),
+            count,
+        })
+        .collect()
+}
+
+/// The number of single character insertions, deletions, or substitutions between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The tags that are meant to be used in a library, with a description of each
+/// (loaded from the `tag_vocabulary` file, a TOML table of tags and descriptions)
+pub struct Vocabulary(BTreeMap<String, String>);
+
+impl Vocabulary {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.to_string_lossy()))?;
+        let tags = toml::from_str(&content)
+            .with_context(|| format!("Could not parse {}", path.to_string_lossy()))?;
+        Ok(Self(tags))
+    }
+
+    pub fn description(&self, tag: &str) -> Option<&str> {
+        self.0.get(tag).map(|d| d.as_str())
+    }
+
+    /// Whether the tag is in the vocabulary, or beneath a tag that is
+    pub fn contains(&self, tag: &str) -> bool {
+        with_ancestors(tag).any(|t| self.0.contains_key(t))
+    }
+
+    /// Tags in the vocabulary that an unknown tag may be a typo of, the most similar first
+    pub fn suggestions(&self, tag: &str) -> Vec<&str> {
+        let max_distance = (tag.chars().count() / 3).max(1);
+        let mut similar: Vec<(usize, &str)> = self
+            .0
+            .keys()
+            .map(|known| (edit_distance(tag, known), known.as_str()))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect();
+        similar.sort();
+        similar.into_iter().take(3).map(|(_, t)| t).collect()
+    }
+
+    /// Describes the problem with a tag that is not in the vocabulary
+    pub fn check(&self, tag: &str) -> Option<String> {
+        if self.contains(tag) {
+            return None;
+        }
+        let suggestions = self.suggestions(tag);
+        if suggestions.is_empty() {
+            return Some(format!("Unknown tag `{tag}`"));
+        }
+        Some(format!(
+            "Unknown tag `{tag}` (did you mean `{}`?)",
+            suggestions.join("`, `")
+        ))
+    }
+
+    /// Prints a warning for each tag that is not in the vocabulary
+    pub fn warn_unknown<'a>(&self, tags: impl IntoIterator<Item = &'a String>) {
+        for problem in tags.into_iter().unique().filter_map(|t| self.check(t)) {
+            eprintln!("WARNING: {problem}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descendants() {
+        assert!(is_within("project/grant-2025/aim1", "project/grant-2025"));
+        assert!(is_within("project/grant-2025", "project/grant-2025"));
+        assert!(is_within("project/grant-2025", "project/"));
+        assert!(!is_within("project/grant-2025-old", "project/grant-2025"));
+        assert!(!is_within("project", "project/grant-2025"));
+    }
+
+    #[test]
+    fn renames_subtrees() {
+        assert_eq!(rename("a/b/c", "a/b", "x").as_deref(), Some("x/c"));
+        assert_eq!(rename("a/b", "a/b", "x/y").as_deref(), Some("x/y"));
+        assert_eq!(rename("a/bc", "a/b", "x"), None);
+    }
+
+    #[test]
+    fn suggests_known_tags() {
+        let vocabulary = Vocabulary(BTreeMap::from([
+            ("to-read".to_string(), String::new()),
+            ("project".to_string(), String::new()),
+        ]));
+        assert!(vocabulary.contains("project/grant-2025"));
+        assert_eq!(vocabulary.check("to-read"), None);
+        assert_eq!(vocabulary.suggestions("toread"), ["to-read"]);
+        assert_eq!(
+            vocabulary.check("porject").as_deref(),
+            Some("Unknown tag `porject` (did you mean `project`?)")
+        );
+        assert_eq!(
+            vocabulary.check("misc").as_deref(),
+            Some("Unknown tag `misc`")
+        );
+    }
+
+    #[test]
+    fn builds_tree() {
+        let entries = [
+            vec!["project/a/aim1".to_string(), "project/a/aim2".to_string()],
+            vec!["project/b".to_string(), "project-old".to_string()],
+        ];
+        let nodes = tree(entries.iter().map(|t| t.as_slice()));
+        let lines: Vec<_> = nodes.iter().map(|n| (n.depth, n.name, n.count)).collect();
+        assert_eq!(
+            lines,
+            [
+                (0, "project", 2),
+                (1, "a", 1),
+                (2, "aim1", 1),
+                (2, "aim2", 1),
+                (1, "b", 1),
+                (0, "project-old", 1),
+            ]
+        );
+    }
+}